@@ -0,0 +1,395 @@
+use core::mem::size_of;
+use core::ptr;
+
+use num::Num;
+
+use misc::is_affine;
+#[cfg(test)]
+use point::{transform_point3, transform_vector3};
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+/// Types that are valid for every bit pattern, which lets the strided
+/// functions read them out of raw vertex buffers.
+///
+/// # Safety
+///
+/// Implement this only for types with no padding and no invalid bit
+/// patterns, such as primitive integers and floats: the strided functions
+/// reinterpret arbitrary bytes as `Self` and write `Self` back as bytes.
+pub unsafe trait Pod: Copy {}
+
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for isize {}
+unsafe impl Pod for u8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for usize {}
+
+#[inline]
+pub fn transform_points<'b, T: Copy + Num>(m: &'b [T; 16], points: &'b [[T; 3]], out: &mut [[T; 3]]) {
+    assert_eq!(points.len(), out.len());
+
+    let m00 = m[0];
+    let m01 = m[1];
+    let m02 = m[2];
+    let m10 = m[4];
+    let m11 = m[5];
+    let m12 = m[6];
+    let m20 = m[8];
+    let m21 = m[9];
+    let m22 = m[10];
+    let m30 = m[12];
+    let m31 = m[13];
+    let m32 = m[14];
+
+    if is_affine(m) {
+        for (o, p) in out.iter_mut().zip(points.iter()) {
+            let x = p[0];
+            let y = p[1];
+            let z = p[2];
+            o[0] = m00 * x + m10 * y + m20 * z + m30;
+            o[1] = m01 * x + m11 * y + m21 * z + m31;
+            o[2] = m02 * x + m12 * y + m22 * z + m32;
+        }
+    } else {
+        let m03 = m[3];
+        let m13 = m[7];
+        let m23 = m[11];
+        let m33 = m[15];
+
+        for (o, p) in out.iter_mut().zip(points.iter()) {
+            let x = p[0];
+            let y = p[1];
+            let z = p[2];
+            let w = m03 * x + m13 * y + m23 * z + m33;
+            let inv_w = if w != T::zero() {T::one() / w} else {T::one()};
+            o[0] = (m00 * x + m10 * y + m20 * z + m30) * inv_w;
+            o[1] = (m01 * x + m11 * y + m21 * z + m31) * inv_w;
+            o[2] = (m02 * x + m12 * y + m22 * z + m32) * inv_w;
+        }
+    }
+}
+#[test]
+fn test_transform_points() {
+    let mut rng = Rng(13);
+    let mut points = [[0f64; 3]; 32];
+    let mut out = [[0f64; 3]; 32];
+    let mut v = [0f64; 3];
+    for p in points.iter_mut() {
+        *p = [rng.next(), rng.next(), rng.next()];
+    }
+    for &m in [rng.trs(false), rng.mat4()].iter() {
+        transform_points(&m, &points, &mut out);
+        for i in 0..points.len() {
+            transform_point3(&mut v, &m, &points[i]);
+            assert_close(&out[i], &v, 1e-12f64);
+        }
+        let mut in_place = points;
+        transform_points_in_place(&m, &mut in_place);
+        assert_eq!(in_place, out);
+    }
+
+    // doubled then moved by (1, 1, 1), and divided through by z
+    let scale = [
+        2f64, 0f64, 0f64, 0f64,
+        0f64, 2f64, 0f64, 0f64,
+        0f64, 0f64, 2f64, 0f64,
+        1f64, 1f64, 1f64, 1f64
+    ];
+    let divide = [
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 1f64,
+        0f64, 0f64, 0f64, 0f64
+    ];
+    let points = [[1f64, 2f64, 3f64], [2f64, 4f64, 2f64]];
+    let mut out = [[0f64; 3]; 2];
+    transform_points(&scale, &points, &mut out);
+    assert_eq!(out, [[3f64, 5f64, 7f64], [5f64, 9f64, 5f64]]);
+    transform_points(&divide, &points, &mut out);
+    assert_eq!(out, [[1f64 / 3f64, 2f64 / 3f64, 1f64], [1f64, 2f64, 1f64]]);
+}
+
+#[inline]
+pub fn transform_points_in_place<'b, T: Copy + Num>(m: &'b [T; 16], points: &mut [[T; 3]]) {
+    let m00 = m[0];
+    let m01 = m[1];
+    let m02 = m[2];
+    let m10 = m[4];
+    let m11 = m[5];
+    let m12 = m[6];
+    let m20 = m[8];
+    let m21 = m[9];
+    let m22 = m[10];
+    let m30 = m[12];
+    let m31 = m[13];
+    let m32 = m[14];
+
+    if is_affine(m) {
+        for p in points.iter_mut() {
+            let x = p[0];
+            let y = p[1];
+            let z = p[2];
+            p[0] = m00 * x + m10 * y + m20 * z + m30;
+            p[1] = m01 * x + m11 * y + m21 * z + m31;
+            p[2] = m02 * x + m12 * y + m22 * z + m32;
+        }
+    } else {
+        let m03 = m[3];
+        let m13 = m[7];
+        let m23 = m[11];
+        let m33 = m[15];
+
+        for p in points.iter_mut() {
+            let x = p[0];
+            let y = p[1];
+            let z = p[2];
+            let w = m03 * x + m13 * y + m23 * z + m33;
+            let inv_w = if w != T::zero() {T::one() / w} else {T::one()};
+            p[0] = (m00 * x + m10 * y + m20 * z + m30) * inv_w;
+            p[1] = (m01 * x + m11 * y + m21 * z + m31) * inv_w;
+            p[2] = (m02 * x + m12 * y + m22 * z + m32) * inv_w;
+        }
+    }
+}
+
+#[inline]
+pub fn transform_vectors<'b, T: Copy + Num>(m: &'b [T; 16], vectors: &'b [[T; 3]], out: &mut [[T; 3]]) {
+    assert_eq!(vectors.len(), out.len());
+
+    let m00 = m[0];
+    let m01 = m[1];
+    let m02 = m[2];
+    let m10 = m[4];
+    let m11 = m[5];
+    let m12 = m[6];
+    let m20 = m[8];
+    let m21 = m[9];
+    let m22 = m[10];
+
+    for (o, v) in out.iter_mut().zip(vectors.iter()) {
+        let x = v[0];
+        let y = v[1];
+        let z = v[2];
+        o[0] = m00 * x + m10 * y + m20 * z;
+        o[1] = m01 * x + m11 * y + m21 * z;
+        o[2] = m02 * x + m12 * y + m22 * z;
+    }
+}
+#[test]
+fn test_transform_vectors() {
+    let mut rng = Rng(17);
+    let mut vectors = [[0f64; 3]; 32];
+    let mut out = [[0f64; 3]; 32];
+    let mut v = [0f64; 3];
+    for p in vectors.iter_mut() {
+        *p = [rng.next(), rng.next(), rng.next()];
+    }
+    let m = rng.mat4();
+    transform_vectors(&m, &vectors, &mut out);
+    for i in 0..vectors.len() {
+        transform_vector3(&mut v, &m, &vectors[i]);
+        assert_close(&out[i], &v, 1e-12f64);
+    }
+    transform_vectors_in_place(&m, &mut vectors);
+    assert_eq!(vectors, out);
+
+    // scaled by (2, 3, 4); the translation and the projective row are ignored
+    let m = [
+        2f64, 0f64, 0f64, 1f64,
+        0f64, 3f64, 0f64, 0f64,
+        0f64, 0f64, 4f64, 0f64,
+        5f64, 6f64, 7f64, 1f64
+    ];
+    let mut out = [[0f64; 3]; 1];
+    transform_vectors(&m, &[[1f64, 2f64, 3f64]], &mut out);
+    assert_eq!(out, [[2f64, 6f64, 12f64]]);
+}
+
+#[inline]
+pub fn transform_vectors_in_place<'b, T: Copy + Num>(m: &'b [T; 16], vectors: &mut [[T; 3]]) {
+    let m00 = m[0];
+    let m01 = m[1];
+    let m02 = m[2];
+    let m10 = m[4];
+    let m11 = m[5];
+    let m12 = m[6];
+    let m20 = m[8];
+    let m21 = m[9];
+    let m22 = m[10];
+
+    for v in vectors.iter_mut() {
+        let x = v[0];
+        let y = v[1];
+        let z = v[2];
+        v[0] = m00 * x + m10 * y + m20 * z;
+        v[1] = m01 * x + m11 * y + m21 * z;
+        v[2] = m02 * x + m12 * y + m22 * z;
+    }
+}
+
+/// Transforms, in place, the `[T; 3]` points found every `stride` bytes of
+/// `data` starting at byte `offset`, e.g. the positions of an interleaved
+/// vertex buffer. Panics if `stride` is smaller than a point.
+#[inline]
+pub fn transform_points_strided<'b, T: Copy + Num + Pod>(m: &'b [T; 16], data: &mut [u8], offset: usize, stride: usize) {
+    let size = size_of::<[T; 3]>();
+    assert!(stride >= size);
+
+    let m00 = m[0];
+    let m01 = m[1];
+    let m02 = m[2];
+    let m10 = m[4];
+    let m11 = m[5];
+    let m12 = m[6];
+    let m20 = m[8];
+    let m21 = m[9];
+    let m22 = m[10];
+    let m30 = m[12];
+    let m31 = m[13];
+    let m32 = m[14];
+
+    let mut i = offset;
+    if is_affine(m) {
+        while i + size <= data.len() {
+            let ptr = data[i..(i + size)].as_mut_ptr() as *mut [T; 3];
+            let p = unsafe { ptr::read_unaligned(ptr) };
+            let x = p[0];
+            let y = p[1];
+            let z = p[2];
+            let o = [
+                m00 * x + m10 * y + m20 * z + m30,
+                m01 * x + m11 * y + m21 * z + m31,
+                m02 * x + m12 * y + m22 * z + m32
+            ];
+            unsafe { ptr::write_unaligned(ptr, o) };
+            i += stride;
+        }
+    } else {
+        let m03 = m[3];
+        let m13 = m[7];
+        let m23 = m[11];
+        let m33 = m[15];
+
+        while i + size <= data.len() {
+            let ptr = data[i..(i + size)].as_mut_ptr() as *mut [T; 3];
+            let p = unsafe { ptr::read_unaligned(ptr) };
+            let x = p[0];
+            let y = p[1];
+            let z = p[2];
+            let w = m03 * x + m13 * y + m23 * z + m33;
+            let inv_w = if w != T::zero() {T::one() / w} else {T::one()};
+            let o = [
+                (m00 * x + m10 * y + m20 * z + m30) * inv_w,
+                (m01 * x + m11 * y + m21 * z + m31) * inv_w,
+                (m02 * x + m12 * y + m22 * z + m32) * inv_w
+            ];
+            unsafe { ptr::write_unaligned(ptr, o) };
+            i += stride;
+        }
+    }
+}
+#[test]
+fn test_transform_points_strided() {
+    // position, normal, uv
+    let vertices = [
+        [1f32, 2f32, 3f32, 0f32, 0f32, 1f32, 0.25f32, 0.5f32],
+        [4f32, 5f32, 6f32, 1f32, 0f32, 0f32, 0.75f32, 1f32],
+        [7f32, 8f32, 9f32, 0f32, 1f32, 0f32, 0f32, 0f32]
+    ];
+    let write = |data: &mut [u8; 96]| {
+        for (i, v) in vertices.iter().enumerate() {
+            for (j, x) in v.iter().enumerate() {
+                data[(i * 8 + j) * 4..(i * 8 + j + 1) * 4].copy_from_slice(&x.to_ne_bytes());
+            }
+        }
+    };
+    let read = |data: &[u8; 96], k: usize| {
+        let mut b = [0u8; 4];
+        b.copy_from_slice(&data[k * 4..(k + 1) * 4]);
+        f32::from_ne_bytes(b)
+    };
+    let mut data = [0u8; 96];
+    write(&mut data);
+
+    // a half turn about x, doubled and moved by (1, 1, 1); the uniform
+    // scale keeps the normals perpendicular
+    let m = [
+        2f32, 0f32, 0f32, 0f32,
+        0f32, -2f32, 0f32, 0f32,
+        0f32, 0f32, -2f32, 0f32,
+        1f32, 1f32, 1f32, 1f32
+    ];
+    transform_points_strided(&m, &mut data, 0, 32);
+    transform_vectors_strided(&m, &mut data, 12, 32);
+    let expected = [
+        3f32, -3f32, -5f32, 0f32, 0f32, -2f32, 0.25f32, 0.5f32,
+        9f32, -9f32, -11f32, 2f32, 0f32, 0f32, 0.75f32, 1f32,
+        15f32, -15f32, -17f32, 0f32, -2f32, 0f32, 0f32, 0f32
+    ];
+    for (k, &e) in expected.iter().enumerate() {
+        assert_eq!(read(&data, k), e);
+    }
+
+    // divided through by z
+    let m = [
+        1f32, 0f32, 0f32, 0f32,
+        0f32, 1f32, 0f32, 0f32,
+        0f32, 0f32, 1f32, 1f32,
+        0f32, 0f32, 0f32, 0f32
+    ];
+    write(&mut data);
+    transform_points_strided(&m, &mut data, 0, 32);
+    for (i, v) in vertices.iter().enumerate() {
+        let mut p = [0f32; 3];
+        transform_point3(&mut p, &m, &[v[0], v[1], v[2]]);
+        assert_eq!([read(&data, i * 8), read(&data, i * 8 + 1), read(&data, i * 8 + 2)], p);
+        assert_eq!(read(&data, i * 8 + 2), 1f32);
+        for (j, x) in v.iter().enumerate().skip(3) {
+            assert_eq!(read(&data, i * 8 + j), *x);
+        }
+    }
+}
+
+/// Same as `transform_points_strided` for directions such as tangents,
+/// ignoring translation. Normals only stay perpendicular under rotation and
+/// uniform scale; otherwise transform them with `point::transform_normal`.
+#[inline]
+pub fn transform_vectors_strided<'b, T: Copy + Num + Pod>(m: &'b [T; 16], data: &mut [u8], offset: usize, stride: usize) {
+    let size = size_of::<[T; 3]>();
+    assert!(stride >= size);
+
+    let m00 = m[0];
+    let m01 = m[1];
+    let m02 = m[2];
+    let m10 = m[4];
+    let m11 = m[5];
+    let m12 = m[6];
+    let m20 = m[8];
+    let m21 = m[9];
+    let m22 = m[10];
+
+    let mut i = offset;
+    while i + size <= data.len() {
+        let ptr = data[i..(i + size)].as_mut_ptr() as *mut [T; 3];
+        let v = unsafe { ptr::read_unaligned(ptr) };
+        let x = v[0];
+        let y = v[1];
+        let z = v[2];
+        let o = [
+            m00 * x + m10 * y + m20 * z,
+            m01 * x + m11 * y + m21 * z,
+            m02 * x + m12 * y + m22 * z
+        ];
+        unsafe { ptr::write_unaligned(ptr, o) };
+        i += stride;
+    }
+}
@@ -0,0 +1,90 @@
+use num::Num;
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthRange {
+    /// OpenGL style normalized depth in `[-1, 1]`.
+    NegativeOneToOne,
+    /// Vulkan, Direct3D and Metal style normalized depth in `[0, 1]`.
+    ZeroToOne,
+}
+
+/// Describes the clip space a projection maps into: the normalized depth
+/// range, whether depth is reversed so that the near plane maps to the upper
+/// end of the range, and whether perspective projections push the far plane
+/// to infinity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClipSpace {
+    pub depth: DepthRange,
+    pub reversed: bool,
+    pub infinite: bool,
+}
+
+impl ClipSpace {
+    #[inline]
+    pub fn opengl() -> Self {
+        ClipSpace {
+            depth: DepthRange::NegativeOneToOne,
+            reversed: false,
+            infinite: false,
+        }
+    }
+    #[inline]
+    pub fn zero_to_one() -> Self {
+        ClipSpace {
+            depth: DepthRange::ZeroToOne,
+            reversed: false,
+            infinite: false,
+        }
+    }
+    #[inline]
+    pub fn reversed(mut self) -> Self {
+        self.reversed = true;
+        self
+    }
+    #[inline]
+    pub fn infinite(mut self) -> Self {
+        self.infinite = true;
+        self
+    }
+
+    /// Normalized depth of the near and far planes.
+    #[inline]
+    pub fn near_far<T: Copy + Num>(&self) -> (T, T) {
+        let (min, max) = match self.depth {
+            DepthRange::NegativeOneToOne => (T::zero() - T::one(), T::one()),
+            DepthRange::ZeroToOne => (T::zero(), T::one()),
+        };
+        if self.reversed {(max, min)} else {(min, max)}
+    }
+
+    /// Maps a normalized depth to a window depth in `[0, 1]`.
+    #[inline]
+    pub fn ndc_to_window<T: Copy + Num>(&self, z: T) -> T {
+        match self.depth {
+            DepthRange::NegativeOneToOne => (z + T::one()) * T::from_f32(0.5f32),
+            DepthRange::ZeroToOne => z,
+        }
+    }
+    /// Maps a window depth in `[0, 1]` to a normalized depth.
+    #[inline]
+    pub fn window_to_ndc<T: Copy + Num>(&self, z: T) -> T {
+        match self.depth {
+            DepthRange::NegativeOneToOne => z * T::from_isize(2isize) - T::one(),
+            DepthRange::ZeroToOne => z,
+        }
+    }
+}
+
+impl Default for ClipSpace {
+    #[inline]
+    fn default() -> Self { Self::opengl() }
+}
+
+#[test]
+fn test_clip_space_near_far() {
+    assert_eq!(ClipSpace::opengl().near_far::<f32>(), (-1f32, 1f32));
+    assert_eq!(ClipSpace::opengl().reversed().near_far::<f32>(), (1f32, -1f32));
+    assert_eq!(ClipSpace::zero_to_one().near_far::<f32>(), (0f32, 1f32));
+    assert_eq!(ClipSpace::zero_to_one().reversed().infinite().near_far::<f32>(), (1f32, 0f32));
+}
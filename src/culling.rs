@@ -0,0 +1,196 @@
+use num::Num;
+use vec3;
+
+use clip::{ClipSpace, DepthRange};
+
+#[cfg(test)]
+use mul::mul;
+#[cfg(test)]
+use transform::{look_at, perspective, perspective_clip};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Intersection {
+    Outside,
+    Intersecting,
+    Inside,
+}
+
+pub const LEFT: usize = 0;
+pub const RIGHT: usize = 1;
+pub const BOTTOM: usize = 2;
+pub const TOP: usize = 3;
+pub const NEAR: usize = 4;
+pub const FAR: usize = 5;
+
+#[inline]
+fn normalize_plane<T: Copy + Num>(out: &mut [T; 4], a: T, b: T, c: T, d: T) {
+    let len = vec3::length_values(a, b, c);
+    let inv_len = if len != T::zero() {T::one() / len} else {T::zero()};
+    out[0] = a * inv_len;
+    out[1] = b * inv_len;
+    out[2] = c * inv_len;
+    out[3] = d * inv_len;
+}
+
+/// Extracts the normalized clip planes of the view-projection `m`
+/// (Gribb-Hartmann), indexed by `LEFT`, `RIGHT`, `BOTTOM`, `TOP`, `NEAR` and
+/// `FAR`. A point `p` is on the inside of plane `[a, b, c, d]` when
+/// `a * p.x + b * p.y + c * p.z + d >= 0`. Assumes OpenGL clip space with
+/// depth in `[-1, 1]`.
+#[inline]
+pub fn frustum_planes<'a, 'b, T: Copy + Num>(out: &'a mut [[T; 4]; 6], m: &'b [T; 16]) -> &'a mut [[T; 4]; 6] {
+    frustum_planes_clip(out, m, &ClipSpace::opengl())
+}
+
+/// Same as `frustum_planes` for projections built for the clip space `clip`.
+/// The far plane of an infinite projection comes out as all zeros, which
+/// every point is inside of.
+#[inline]
+pub fn frustum_planes_clip<'a, 'b, T: Copy + Num>(out: &'a mut [[T; 4]; 6], m: &'b [T; 16], clip: &'b ClipSpace) -> &'a mut [[T; 4]; 6] {
+    let (x0, x1, x2, x3) = (m[0], m[4], m[8], m[12]);
+    let (y0, y1, y2, y3) = (m[1], m[5], m[9], m[13]);
+    let (z0, z1, z2, z3) = (m[2], m[6], m[10], m[14]);
+    let (w0, w1, w2, w3) = (m[3], m[7], m[11], m[15]);
+    let (near, far) = if clip.reversed {(FAR, NEAR)} else {(NEAR, FAR)};
+
+    normalize_plane(&mut out[LEFT], w0 + x0, w1 + x1, w2 + x2, w3 + x3);
+    normalize_plane(&mut out[RIGHT], w0 - x0, w1 - x1, w2 - x2, w3 - x3);
+    normalize_plane(&mut out[BOTTOM], w0 + y0, w1 + y1, w2 + y2, w3 + y3);
+    normalize_plane(&mut out[TOP], w0 - y0, w1 - y1, w2 - y2, w3 - y3);
+    match clip.depth {
+        DepthRange::NegativeOneToOne => normalize_plane(&mut out[near], w0 + z0, w1 + z1, w2 + z2, w3 + z3),
+        DepthRange::ZeroToOne => normalize_plane(&mut out[near], z0, z1, z2, z3),
+    }
+    normalize_plane(&mut out[far], w0 - z0, w1 - z1, w2 - z2, w3 - z3);
+    out
+}
+
+#[inline]
+fn distance<T: Copy + Num>(plane: &[T; 4], x: T, y: T, z: T) -> T {
+    plane[0] * x + plane[1] * y + plane[2] * z + plane[3]
+}
+
+#[inline]
+pub fn point_in_frustum<'b, T: Copy + Num>(planes: &'b [[T; 4]; 6], p: &'b [T; 3]) -> bool {
+    planes.iter().all(|plane| distance(plane, p[0], p[1], p[2]) >= T::zero())
+}
+
+#[inline]
+pub fn sphere_in_frustum<'b, T: Copy + Num>(planes: &'b [[T; 4]; 6], center: &'b [T; 3], radius: T) -> Intersection {
+    let mut result = Intersection::Inside;
+
+    for plane in planes.iter() {
+        let d = distance(plane, center[0], center[1], center[2]);
+
+        if d < T::zero() - radius {
+            return Intersection::Outside;
+        } else if d < radius {
+            result = Intersection::Intersecting;
+        }
+    }
+    result
+}
+
+/// Tests an axis-aligned box against `planes` using the corners nearest to
+/// and farthest along each plane normal. Boxes that straddle two planes
+/// outside a frustum corner may be reported as `Intersecting`.
+#[inline]
+pub fn aabb_in_frustum<'b, T: Copy + Num>(planes: &'b [[T; 4]; 6], min: &'b [T; 3], max: &'b [T; 3]) -> Intersection {
+    let mut result = Intersection::Inside;
+
+    for plane in planes.iter() {
+        let (px, nx) = if plane[0] >= T::zero() {(max[0], min[0])} else {(min[0], max[0])};
+        let (py, ny) = if plane[1] >= T::zero() {(max[1], min[1])} else {(min[1], max[1])};
+        let (pz, nz) = if plane[2] >= T::zero() {(max[2], min[2])} else {(min[2], max[2])};
+
+        if distance(plane, px, py, pz) < T::zero() {
+            return Intersection::Outside;
+        } else if distance(plane, nx, ny, nz) < T::zero() {
+            result = Intersection::Intersecting;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+fn view_projection() -> [f64; 16] {
+    let mut proj = [0f64; 16];
+    let mut view = [0f64; 16];
+    let mut m = [0f64; 16];
+    perspective(&mut proj, 0.5f64 * ::core::f64::consts::PI, 1f64, 1f64, 100f64);
+    look_at(&mut view, &[0f64, 0f64, 10f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    mul(&mut m, &proj, &view);
+    m
+}
+#[test]
+fn test_frustum_planes() {
+    let mut planes = [[0f64; 4]; 6];
+    frustum_planes(&mut planes, &view_projection());
+
+    // camera at z = 10 looking down -z with a 90 degree field of view
+    assert!((planes[NEAR][2] + 1f64).abs() < 1e-12f64);
+    assert!((planes[NEAR][3] - 9f64).abs() < 1e-12f64);
+    assert!((planes[FAR][2] - 1f64).abs() < 1e-12f64);
+    assert!((planes[FAR][3] - 90f64).abs() < 1e-9f64);
+    let h = 0.5f64.sqrt();
+    assert!((planes[LEFT][0] - h).abs() < 1e-12f64);
+    assert!((planes[RIGHT][0] + h).abs() < 1e-12f64);
+}
+#[test]
+fn test_point_in_frustum() {
+    let mut planes = [[0f64; 4]; 6];
+    frustum_planes(&mut planes, &view_projection());
+
+    assert!(point_in_frustum(&planes, &[0f64, 0f64, 0f64]));
+    assert!(point_in_frustum(&planes, &[9f64, -9f64, -0.5f64]));
+    assert!(!point_in_frustum(&planes, &[0f64, 0f64, 9.5f64]));
+    assert!(!point_in_frustum(&planes, &[0f64, 0f64, -91f64]));
+    assert!(!point_in_frustum(&planes, &[11f64, 0f64, 0f64]));
+}
+#[test]
+fn test_sphere_in_frustum() {
+    let mut planes = [[0f64; 4]; 6];
+    frustum_planes(&mut planes, &view_projection());
+
+    assert_eq!(sphere_in_frustum(&planes, &[0f64, 0f64, 0f64], 1f64), Intersection::Inside);
+    assert_eq!(sphere_in_frustum(&planes, &[10f64, 0f64, 0f64], 1f64), Intersection::Intersecting);
+    assert_eq!(sphere_in_frustum(&planes, &[20f64, 0f64, 0f64], 1f64), Intersection::Outside);
+    assert_eq!(sphere_in_frustum(&planes, &[0f64, 0f64, 12f64], 1f64), Intersection::Outside);
+}
+#[test]
+fn test_aabb_in_frustum() {
+    let mut planes = [[0f64; 4]; 6];
+    frustum_planes(&mut planes, &view_projection());
+
+    assert_eq!(aabb_in_frustum(&planes, &[-1f64, -1f64, -1f64], &[1f64, 1f64, 1f64]), Intersection::Inside);
+    assert_eq!(aabb_in_frustum(&planes, &[9f64, -1f64, -1f64], &[11f64, 1f64, 1f64]), Intersection::Intersecting);
+    assert_eq!(aabb_in_frustum(&planes, &[19f64, -1f64, -1f64], &[21f64, 1f64, 1f64]), Intersection::Outside);
+    assert_eq!(aabb_in_frustum(&planes, &[-1f64, -1f64, -200f64], &[1f64, 1f64, 200f64]), Intersection::Intersecting);
+}
+#[test]
+fn test_frustum_planes_clip() {
+    let configs = [
+        ClipSpace::opengl(),
+        ClipSpace::opengl().reversed(),
+        ClipSpace::zero_to_one(),
+        ClipSpace::zero_to_one().reversed(),
+    ];
+    let mut proj = [0f64; 16];
+    let mut planes = [[0f64; 4]; 6];
+
+    for clip in configs.iter() {
+        perspective_clip(&mut proj, 1f64, 1f64, 1f64, 10f64, clip);
+        frustum_planes_clip(&mut planes, &proj, clip);
+        assert!((planes[NEAR][2] + 1f64).abs() < 1e-12f64);
+        assert!((planes[NEAR][3] + 1f64).abs() < 1e-12f64);
+        assert!((planes[FAR][2] - 1f64).abs() < 1e-12f64);
+        assert!((planes[FAR][3] - 10f64).abs() < 1e-12f64);
+
+        perspective_clip(&mut proj, 1f64, 1f64, 1f64, 10f64, &clip.infinite());
+        frustum_planes_clip(&mut planes, &proj, &clip.infinite());
+        assert!((planes[NEAR][3] + 1f64).abs() < 1e-12f64);
+        assert!(point_in_frustum(&planes, &[0f64, 0f64, -1e9f64]));
+        assert!(!point_in_frustum(&planes, &[0f64, 0f64, -0.5f64]));
+    }
+}
@@ -0,0 +1,606 @@
+use num::Num;
+
+use misc::{abs, epsilon};
+use set::identity;
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+// All routines work on the leading `n` rows and columns, so the 3x3 variants
+// share them with `n = 3` and leave the rest of their outputs as identity.
+
+#[inline]
+fn column_dot<T: Copy + Num>(a: &[T; 16], b: &[T; 16], i: usize, j: usize, n: usize) -> T {
+    let mut sum = T::zero();
+    for k in 0..n {
+        sum = sum + a[i * 4 + k] * b[j * 4 + k];
+    }
+    sum
+}
+
+#[inline]
+fn swap_columns<T: Copy + Num>(a: &mut [T; 16], i: usize, j: usize) {
+    for k in 0..4 {
+        a.swap(i * 4 + k, j * 4 + k);
+    }
+}
+
+// one-sided Jacobi: rotates pairs of columns of `a` until they are mutually
+// orthogonal, accumulating the rotations in `v`, then reads off the singular
+// values in decreasing order
+fn jacobi_svd<T: Copy + Num>(u: &mut [T; 16], sigma: &mut [T; 4], v: &mut [T; 16], m: &[T; 16], n: usize) {
+    let eps = epsilon::<T>();
+    let one = T::one();
+    let two = T::from_isize(2isize);
+    let mut a = [T::zero(); 16];
+    identity(&mut a);
+    identity(v);
+    for j in 0..n {
+        for i in 0..n {
+            a[j * 4 + i] = m[j * 4 + i];
+        }
+    }
+
+    for _ in 0..64 {
+        let mut rotated = false;
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let alpha = column_dot(&a, &a, p, p, n);
+                let beta = column_dot(&a, &a, q, q, n);
+                let gamma = column_dot(&a, &a, p, q, n);
+
+                if gamma == T::zero() || abs(gamma) <= eps * (alpha * beta).sqrt() {
+                    continue;
+                }
+                rotated = true;
+
+                let zeta = (beta - alpha) / (two * gamma);
+                let t = (if zeta < T::zero() {T::zero() - one} else {one}) / (abs(zeta) + (one + zeta * zeta).sqrt());
+                let c = one / (one + t * t).sqrt();
+                let s = c * t;
+
+                for k in 0..4 {
+                    let ap = a[p * 4 + k];
+                    let aq = a[q * 4 + k];
+                    a[p * 4 + k] = c * ap - s * aq;
+                    a[q * 4 + k] = s * ap + c * aq;
+                    let vp = v[p * 4 + k];
+                    let vq = v[q * 4 + k];
+                    v[p * 4 + k] = c * vp - s * vq;
+                    v[q * 4 + k] = s * vp + c * vq;
+                }
+            }
+        }
+
+        if !rotated {
+            break;
+        }
+    }
+
+    for (j, s) in sigma.iter_mut().enumerate() {
+        *s = if j < n {column_dot(&a, &a, j, j, n).sqrt()} else {T::zero()};
+    }
+    for j in 0..n {
+        let mut max = j;
+        for k in (j + 1)..n {
+            if sigma[k] > sigma[max] {
+                max = k;
+            }
+        }
+        if max != j {
+            sigma.swap(j, max);
+            swap_columns(&mut a, j, max);
+            swap_columns(v, j, max);
+        }
+    }
+
+    identity(u);
+    let tiny = T::from_isize(n as isize) * eps * sigma[0];
+    let mut rank = 0;
+    for j in 0..n {
+        if sigma[j] > tiny {
+            for k in 0..n {
+                u[j * 4 + k] = a[j * 4 + k] / sigma[j];
+            }
+            rank += 1;
+        }
+    }
+
+    // complete the basis for columns with a vanishing singular value, using
+    // the unit vector that is least covered by the columns so far
+    for j in rank..n {
+        let mut best = [T::zero(); 16];
+        let mut best_len = T::zero();
+        for e in 0..n {
+            let mut c = [T::zero(); 16];
+            c[e] = one;
+            for i in 0..j {
+                let d = column_dot(u, &c, i, 0, n);
+                for k in 0..n {
+                    c[k] = c[k] - d * u[i * 4 + k];
+                }
+            }
+            let len = column_dot(&c, &c, 0, 0, n).sqrt();
+            if len > best_len {
+                best = c;
+                best_len = len;
+            }
+        }
+        for k in 0..n {
+            u[j * 4 + k] = best[k] / best_len;
+        }
+    }
+}
+
+/// Singular value decomposition `m = u * diag(sigma) * v^T` with orthogonal
+/// `u` and `v` and non-negative singular values in decreasing order. Uses
+/// one-sided Jacobi rotations, so results are deterministic.
+#[inline]
+pub fn svd<'b, T: Copy + Num>(u: &mut [T; 16], sigma: &mut [T; 4], v: &mut [T; 16], m: &'b [T; 16]) {
+    jacobi_svd(u, sigma, v, m, 4);
+}
+
+/// `svd` of the upper 3x3 of `m`. `u` and `v` have identity in their last row
+/// and column.
+#[inline]
+pub fn svd3<'b, T: Copy + Num>(u: &mut [T; 16], sigma: &mut [T; 3], v: &mut [T; 16], m: &'b [T; 16]) {
+    let mut s = [T::zero(); 4];
+    jacobi_svd(u, &mut s, v, m, 3);
+    sigma[0] = s[0];
+    sigma[1] = s[1];
+    sigma[2] = s[2];
+}
+#[cfg(test)]
+fn assert_orthogonal(m: &[f64; 16]) {
+    let mut t = [0f64; 16];
+    let mut p = [0f64; 16];
+    ::misc::transpose(&mut t, m);
+    ::mul::mul(&mut p, &t, m);
+    assert_close(&p, &::create::new_identity::<f64>(), 1e-12f64);
+}
+#[test]
+fn test_svd() {
+    let mut rng = Rng(83);
+    let mut u = [0f64; 16];
+    let mut v = [0f64; 16];
+    let mut vt = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    let mut out = [0f64; 16];
+    let mut sigma = [0f64; 4];
+    let mut sigma3 = [0f64; 3];
+
+    let mut rank2 = rng.mat4();
+    for k in 0..4 {
+        rank2[8 + k] = rank2[k] - 2f64 * rank2[4 + k];
+        rank2[12 + k] = 3f64 * rank2[k];
+    }
+
+    for i in 0..21 {
+        let m = if i == 20 {rank2} else {rng.mat4()};
+        svd(&mut u, &mut sigma, &mut v, &m);
+        assert_orthogonal(&u);
+        assert_orthogonal(&v);
+        assert!(sigma[0] >= sigma[1] && sigma[1] >= sigma[2] && sigma[2] >= sigma[3] && sigma[3] >= 0f64);
+        let d = [
+            sigma[0], 0f64, 0f64, 0f64,
+            0f64, sigma[1], 0f64, 0f64,
+            0f64, 0f64, sigma[2], 0f64,
+            0f64, 0f64, 0f64, sigma[3]
+        ];
+        ::misc::transpose(&mut vt, &v);
+        ::mul::mul(&mut tmp, &u, &d);
+        ::mul::mul(&mut out, &tmp, &vt);
+        assert_close(&out, &m, 1e-12f64);
+
+        svd3(&mut u, &mut sigma3, &mut v, &m);
+        assert_orthogonal(&u);
+        assert_orthogonal(&v);
+        assert_close(&u[12..], &[0f64, 0f64, 0f64, 1f64], 0f64);
+        let d = [
+            sigma3[0], 0f64, 0f64, 0f64,
+            0f64, sigma3[1], 0f64, 0f64,
+            0f64, 0f64, sigma3[2], 0f64,
+            0f64, 0f64, 0f64, 1f64
+        ];
+        ::misc::transpose(&mut vt, &v);
+        ::mul::mul(&mut tmp, &u, &d);
+        ::mul::mul(&mut out, &tmp, &vt);
+        for j in 0..3 {
+            assert_close(&out[j * 4..j * 4 + 3], &m[j * 4..j * 4 + 3], 1e-12f64);
+        }
+    }
+    assert!(sigma[2] < 1e-12f64 && sigma[3] < 1e-12f64);
+
+    svd(&mut u, &mut sigma, &mut v, &[0f64; 16]);
+    assert_eq!(sigma, [0f64; 4]);
+    assert_orthogonal(&u);
+
+    // a negative diagonal entry: the sign goes into u or v, and the
+    // magnitudes come out sorted
+    svd(&mut u, &mut sigma, &mut v, &[
+        3f64, 0f64, 0f64, 0f64,
+        0f64, -2f64, 0f64, 0f64,
+        0f64, 0f64, 5f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]);
+    assert_close(&sigma, &[5f64, 3f64, 2f64, 1f64], 1e-15f64);
+}
+
+fn polar_n<T: Copy + Num>(r: &mut [T; 16], s: &mut [T; 16], m: &[T; 16], n: usize) {
+    let mut u = [T::zero(); 16];
+    let mut v = [T::zero(); 16];
+    let mut sigma = [T::zero(); 4];
+    jacobi_svd(&mut u, &mut sigma, &mut v, m, n);
+
+    identity(r);
+    identity(s);
+    for j in 0..n {
+        for i in 0..n {
+            let mut rij = T::zero();
+            let mut sij = T::zero();
+            for k in 0..n {
+                rij = rij + u[k * 4 + i] * v[k * 4 + j];
+                sij = sij + v[k * 4 + i] * sigma[k] * v[k * 4 + j];
+            }
+            r[j * 4 + i] = rij;
+            s[j * 4 + i] = sij;
+        }
+    }
+}
+
+/// Polar decomposition `m = r * s` with orthogonal `r` and symmetric positive
+/// semi-definite `s`. `r` is mirrored when `m` is; see
+/// `transform::nearest_rotation` for the closest proper rotation.
+#[inline]
+pub fn polar<'b, T: Copy + Num>(r: &mut [T; 16], s: &mut [T; 16], m: &'b [T; 16]) {
+    polar_n(r, s, m, 4);
+}
+
+/// `polar` of the upper 3x3 of `m`, such as a deformation gradient.
+#[inline]
+pub fn polar3<'b, T: Copy + Num>(r: &mut [T; 16], s: &mut [T; 16], m: &'b [T; 16]) {
+    polar_n(r, s, m, 3);
+}
+#[test]
+fn test_polar() {
+    let mut rng = Rng(89);
+    let mut r = [0f64; 16];
+    let mut s = [0f64; 16];
+    let mut st = [0f64; 16];
+    let mut out = [0f64; 16];
+    let mut u = [0f64; 16];
+    let mut v = [0f64; 16];
+    let mut sigma = [0f64; 4];
+    for _ in 0..20 {
+        let m = rng.mat4();
+        polar(&mut r, &mut s, &m);
+        assert_orthogonal(&r);
+        ::misc::transpose(&mut st, &s);
+        assert_close(&s, &st, 1e-12f64);
+        svd(&mut u, &mut sigma, &mut v, &s);
+        assert!(sigma.iter().all(|x| *x >= 0f64));
+        ::mul::mul(&mut out, &r, &s);
+        assert_close(&out, &m, 1e-12f64);
+
+        let m = rng.trs(false);
+        polar3(&mut r, &mut s, &m);
+        assert_orthogonal(&r);
+        ::misc::transpose(&mut st, &s);
+        assert_close(&s, &st, 1e-12f64);
+        ::mul::mul(&mut out, &r, &s);
+        assert_close(&out[..12], &m[..12], 1e-12f64);
+        assert_close(&out[12..], &[0f64, 0f64, 0f64, 1f64], 0f64);
+    }
+
+    // a mirror stays in r rather than making s indefinite
+    polar(&mut r, &mut s, &[
+        -2f64, 0f64, 0f64, 0f64,
+        0f64, 3f64, 0f64, 0f64,
+        0f64, 0f64, 4f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]);
+    assert_close(&r, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+    assert_close(&s, &[
+        2f64, 0f64, 0f64, 0f64,
+        0f64, 3f64, 0f64, 0f64,
+        0f64, 0f64, 4f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+}
+
+fn householder_qr<T: Copy + Num>(q: &mut [T; 16], r: &mut [T; 16], m: &[T; 16], n: usize) {
+    let two = T::from_isize(2isize);
+    identity(q);
+    identity(r);
+    for j in 0..n {
+        for i in 0..n {
+            r[j * 4 + i] = m[j * 4 + i];
+        }
+    }
+
+    for k in 0..(n - 1) {
+        let mut x = [T::zero(); 4];
+        let mut norm = T::zero();
+        for (xi, &ri) in x[k..n].iter_mut().zip(&r[k * 4 + k..k * 4 + n]) {
+            *xi = ri;
+            norm = norm + ri * ri;
+        }
+        norm = norm.sqrt();
+        if norm == T::zero() {
+            continue;
+        }
+
+        // reflect x onto -sign(x_k) |x| e_k, away from it to avoid cancellation
+        let alpha = if x[k] < T::zero() {norm} else {T::zero() - norm};
+        x[k] = x[k] - alpha;
+        let mut vv = T::zero();
+        for &xi in &x[k..n] {
+            vv = vv + xi * xi;
+        }
+        if vv == T::zero() {
+            continue;
+        }
+
+        for j in 0..n {
+            let mut f = T::zero();
+            for i in k..n {
+                f = f + x[i] * r[j * 4 + i];
+            }
+            f = two * f / vv;
+            for i in k..n {
+                r[j * 4 + i] = r[j * 4 + i] - f * x[i];
+            }
+        }
+        for i in 0..n {
+            let mut f = T::zero();
+            for l in k..n {
+                f = f + q[l * 4 + i] * x[l];
+            }
+            f = two * f / vv;
+            for l in k..n {
+                q[l * 4 + i] = q[l * 4 + i] - f * x[l];
+            }
+        }
+        for i in (k + 1)..n {
+            r[k * 4 + i] = T::zero();
+        }
+    }
+
+    // a non-negative diagonal makes the factorization unique for full rank m
+    for k in 0..n {
+        if r[k * 4 + k] < T::zero() {
+            for j in 0..n {
+                r[j * 4 + k] = T::zero() - r[j * 4 + k];
+                q[k * 4 + j] = T::zero() - q[k * 4 + j];
+            }
+        }
+    }
+}
+
+/// QR decomposition `m = q * r` by Householder reflections, with orthogonal
+/// `q` and upper triangular `r` whose diagonal is non-negative.
+#[inline]
+pub fn qr<'b, T: Copy + Num>(q: &mut [T; 16], r: &mut [T; 16], m: &'b [T; 16]) {
+    householder_qr(q, r, m, 4);
+}
+
+/// `qr` of the upper 3x3 of `m`.
+#[inline]
+pub fn qr3<'b, T: Copy + Num>(q: &mut [T; 16], r: &mut [T; 16], m: &'b [T; 16]) {
+    householder_qr(q, r, m, 3);
+}
+#[test]
+fn test_qr() {
+    let mut rng = Rng(97);
+    let mut q = [0f64; 16];
+    let mut r = [0f64; 16];
+    let mut out = [0f64; 16];
+    for _ in 0..20 {
+        let m = rng.mat4();
+        qr(&mut q, &mut r, &m);
+        assert_orthogonal(&q);
+        for j in 0..4 {
+            assert!(r[j * 4 + j] >= 0f64);
+            for i in (j + 1)..4 {
+                assert_eq!(r[j * 4 + i], 0f64);
+            }
+        }
+        ::mul::mul(&mut out, &q, &r);
+        assert_close(&out, &m, 1e-12f64);
+
+        qr3(&mut q, &mut r, &m);
+        assert_orthogonal(&q);
+        assert_eq!([r[2], r[1], r[6]], [0f64, 0f64, 0f64]);
+        ::mul::mul(&mut out, &q, &r);
+        for j in 0..3 {
+            assert_close(&out[j * 4..j * 4 + 3], &m[j * 4..j * 4 + 3], 1e-12f64);
+        }
+    }
+
+    // (-3, 4) reflects onto +5 directly, while the second step lands on -2
+    // and relies on the sign fix-up; q comes out as a reflection
+    qr(&mut q, &mut r, &[
+        -3f64, 4f64, 0f64, 0f64,
+        1f64, 2f64, 0f64, 0f64,
+        0f64, 0f64, 2f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]);
+    assert_close(&q, &[
+        -0.6f64, 0.8f64, 0f64, 0f64,
+        0.8f64, 0.6f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+    assert_close(&r, &[
+        5f64, 0f64, 0f64, 0f64,
+        1f64, 2f64, 0f64, 0f64,
+        0f64, 0f64, 2f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+}
+
+// cyclic Jacobi on the symmetric part of the leading `n` x `n` block of `m`
+fn jacobi_eigen<T: Copy + Num>(vectors: &mut [T; 16], values: &mut [T; 4], m: &[T; 16], n: usize) {
+    let eps = epsilon::<T>();
+    let one = T::one();
+    let half = T::from_f32(0.5f32);
+    let mut a = [T::zero(); 16];
+    identity(vectors);
+
+    let mut norm = T::zero();
+    for j in 0..n {
+        for i in 0..n {
+            a[j * 4 + i] = half * (m[j * 4 + i] + m[i * 4 + j]);
+            norm = norm + a[j * 4 + i] * a[j * 4 + i];
+        }
+    }
+    let tol = eps * eps * norm;
+
+    for _ in 0..64 {
+        let mut off = T::zero();
+        for p in 0..n {
+            for q in (p + 1)..n {
+                off = off + a[q * 4 + p] * a[q * 4 + p];
+            }
+        }
+        if off <= tol {
+            break;
+        }
+
+        for p in 0..n {
+            for q in (p + 1)..n {
+                let apq = a[q * 4 + p];
+                if apq == T::zero() {
+                    continue;
+                }
+
+                let theta = (a[q * 4 + q] - a[p * 4 + p]) / (T::from_isize(2isize) * apq);
+                let t = (if theta < T::zero() {T::zero() - one} else {one}) / (abs(theta) + (theta * theta + one).sqrt());
+                let c = one / (t * t + one).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let akp = a[p * 4 + k];
+                    let akq = a[q * 4 + k];
+                    a[p * 4 + k] = c * akp - s * akq;
+                    a[q * 4 + k] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let apk = a[k * 4 + p];
+                    let aqk = a[k * 4 + q];
+                    a[k * 4 + p] = c * apk - s * aqk;
+                    a[k * 4 + q] = s * apk + c * aqk;
+                }
+                a[q * 4 + p] = T::zero();
+                a[p * 4 + q] = T::zero();
+
+                for k in 0..n {
+                    let vp = vectors[p * 4 + k];
+                    let vq = vectors[q * 4 + k];
+                    vectors[p * 4 + k] = c * vp - s * vq;
+                    vectors[q * 4 + k] = s * vp + c * vq;
+                }
+            }
+        }
+    }
+
+    for j in 0..4 {
+        values[j] = if j < n {a[j * 4 + j]} else {T::zero()};
+    }
+    for j in 0..n {
+        let mut max = j;
+        for k in (j + 1)..n {
+            if values[k] > values[max] {
+                max = k;
+            }
+        }
+        if max != j {
+            values.swap(j, max);
+            swap_columns(vectors, j, max);
+        }
+    }
+}
+
+/// Eigen-decomposition `m = vectors * diag(values) * vectors^T` of a symmetric
+/// matrix by cyclic Jacobi rotations. Eigenvalues are in decreasing order and
+/// the columns of `vectors` are the matching orthonormal eigenvectors. Only
+/// the symmetric part of `m` is used.
+#[inline]
+pub fn eigen_symmetric<'b, T: Copy + Num>(vectors: &mut [T; 16], values: &mut [T; 4], m: &'b [T; 16]) {
+    jacobi_eigen(vectors, values, m, 4);
+}
+
+/// `eigen_symmetric` for a column major 3x3 matrix, as taken by `from_mat3`.
+#[inline]
+pub fn eigen_symmetric3<'b, T: Copy + Num>(vectors: &mut [T; 9], values: &mut [T; 3], m: &'b [T; 9]) {
+    let mut a = [T::zero(); 16];
+    let mut v = [T::zero(); 16];
+    let mut w = [T::zero(); 4];
+    ::set::from_mat3(&mut a, m);
+    jacobi_eigen(&mut v, &mut w, &a, 3);
+
+    for j in 0..3 {
+        values[j] = w[j];
+        for i in 0..3 {
+            vectors[j * 3 + i] = v[j * 4 + i];
+        }
+    }
+}
+#[test]
+fn test_eigen_symmetric() {
+    let mut rng = Rng(101);
+    let mut vectors = [0f64; 16];
+    let mut vt = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    let mut out = [0f64; 16];
+    let mut values = [0f64; 4];
+    for i in 0..21 {
+        let r = rng.mat4();
+        let mut m = [0f64; 16];
+        ::misc::transpose(&mut tmp, &r);
+        ::mul::mul(&mut m, &tmp, &r);
+        if i == 20 {
+            // a repeated eigenvalue
+            m = [
+                2f64, 0f64, 0f64, 0f64,
+                0f64, 1f64, 1f64, 0f64,
+                0f64, 1f64, 1f64, 0f64,
+                0f64, 0f64, 0f64, 2f64
+            ];
+        }
+
+        eigen_symmetric(&mut vectors, &mut values, &m);
+        assert_orthogonal(&vectors);
+        assert!(values[0] >= values[1] && values[1] >= values[2] && values[2] >= values[3]);
+        let d = [
+            values[0], 0f64, 0f64, 0f64,
+            0f64, values[1], 0f64, 0f64,
+            0f64, 0f64, values[2], 0f64,
+            0f64, 0f64, 0f64, values[3]
+        ];
+        ::misc::transpose(&mut vt, &vectors);
+        ::mul::mul(&mut tmp, &vectors, &d);
+        ::mul::mul(&mut out, &tmp, &vt);
+        assert_close(&out, &m, 1e-12f64);
+    }
+    assert_close(&values, &[2f64, 2f64, 2f64, 0f64], 1e-12f64);
+
+    let mut vectors3 = [0f64; 9];
+    let mut values3 = [0f64; 3];
+    let m3 = [4f64, 1f64, 0f64, 1f64, 3f64, -1f64, 0f64, -1f64, 2f64];
+    eigen_symmetric3(&mut vectors3, &mut values3, &m3);
+    assert!(values3[0] > values3[1] && values3[1] > values3[2]);
+    for j in 0..3 {
+        let v = &vectors3[j * 3..j * 3 + 3];
+        for i in 0..3 {
+            let mv = m3[i] * v[0] + m3[3 + i] * v[1] + m3[6 + i] * v[2];
+            assert!((mv - values3[j] * v[i]).abs() < 1e-12f64);
+        }
+    }
+}
@@ -0,0 +1,292 @@
+use num::Num;
+use signed::Signed;
+
+use misc::epsilon;
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+/// Axis sequence of an Euler angle triple: the six Tait–Bryan orders followed
+/// by the six proper Euler orders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EulerAxes {
+    Xyz,
+    Xzy,
+    Yxz,
+    Yzx,
+    Zxy,
+    Zyx,
+    Xyx,
+    Xzx,
+    Yxy,
+    Yzy,
+    Zxz,
+    Zyz,
+}
+
+impl EulerAxes {
+    #[inline]
+    pub fn indices(&self) -> [usize; 3] {
+        match *self {
+            EulerAxes::Xyz => [0, 1, 2],
+            EulerAxes::Xzy => [0, 2, 1],
+            EulerAxes::Yxz => [1, 0, 2],
+            EulerAxes::Yzx => [1, 2, 0],
+            EulerAxes::Zxy => [2, 0, 1],
+            EulerAxes::Zyx => [2, 1, 0],
+            EulerAxes::Xyx => [0, 1, 0],
+            EulerAxes::Xzx => [0, 2, 0],
+            EulerAxes::Yxy => [1, 0, 1],
+            EulerAxes::Yzy => [1, 2, 1],
+            EulerAxes::Zxz => [2, 0, 2],
+            EulerAxes::Zyz => [2, 1, 2],
+        }
+    }
+    #[inline]
+    pub fn reversed(&self) -> Self {
+        match *self {
+            EulerAxes::Xyz => EulerAxes::Zyx,
+            EulerAxes::Xzy => EulerAxes::Yzx,
+            EulerAxes::Yxz => EulerAxes::Zxy,
+            EulerAxes::Yzx => EulerAxes::Xzy,
+            EulerAxes::Zxy => EulerAxes::Yxz,
+            EulerAxes::Zyx => EulerAxes::Xyz,
+            proper => proper,
+        }
+    }
+    #[inline]
+    pub fn is_proper(&self) -> bool {
+        let i = self.indices();
+        i[0] == i[2]
+    }
+}
+
+/// An axis sequence together with its convention. Intrinsic angles rotate
+/// about the axes of the frame as it is being rotated, so `Xyz` is
+/// `Rx(a) * Ry(b) * Rz(c)`. Extrinsic angles rotate about the fixed world
+/// axes, so `Xyz` is `Rz(c) * Ry(b) * Rx(a)`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EulerOrder {
+    pub axes: EulerAxes,
+    pub extrinsic: bool,
+}
+
+impl EulerOrder {
+    #[inline]
+    pub fn intrinsic(axes: EulerAxes) -> Self {
+        EulerOrder {
+            axes,
+            extrinsic: false,
+        }
+    }
+    #[inline]
+    pub fn extrinsic(axes: EulerAxes) -> Self {
+        EulerOrder {
+            axes,
+            extrinsic: true,
+        }
+    }
+}
+
+// row-major rotation about a single axis
+#[inline]
+fn axis_rotation<T: Copy + Signed>(axis: usize, angle: T) -> [[T; 3]; 3] {
+    let s = angle.sin();
+    let c = angle.cos();
+    let o = T::one();
+    let n = T::zero();
+    match axis {
+        0 => [[o, n, n], [n, c, -s], [n, s, c]],
+        1 => [[c, n, s], [n, o, n], [-s, n, c]],
+        _ => [[c, -s, n], [s, c, n], [n, n, o]],
+    }
+}
+
+#[inline]
+fn mul3<T: Copy + Num>(a: &[[T; 3]; 3], b: &[[T; 3]; 3]) -> [[T; 3]; 3] {
+    let mut out = [[T::zero(); 3]; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            out[i][j] = a[i][0] * b[0][j] + a[i][1] * b[1][j] + a[i][2] * b[2][j];
+        }
+    }
+    out
+}
+
+/// Sets `out` to the rotation described by the angles `a`, `b` and `c` about
+/// the axes of `order`, in radians.
+#[inline]
+pub fn from_euler<'a, T: Copy + Signed>(out: &'a mut [T; 16], order: EulerOrder, a: T, b: T, c: T) -> &'a mut [T; 16] {
+    let (axes, a, c) = if order.extrinsic {
+        (order.axes.reversed(), c, a)
+    } else {
+        (order.axes, a, c)
+    };
+    let i = axes.indices();
+    let r = mul3(&mul3(&axis_rotation(i[0], a), &axis_rotation(i[1], b)), &axis_rotation(i[2], c));
+
+    out[0] = r[0][0];
+    out[1] = r[1][0];
+    out[2] = r[2][0];
+    out[3] = T::zero();
+    out[4] = r[0][1];
+    out[5] = r[1][1];
+    out[6] = r[2][1];
+    out[7] = T::zero();
+    out[8] = r[0][2];
+    out[9] = r[1][2];
+    out[10] = r[2][2];
+    out[11] = T::zero();
+    out[12] = T::zero();
+    out[13] = T::zero();
+    out[14] = T::zero();
+    out[15] = T::one();
+    out
+}
+#[test]
+fn test_from_euler() {
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    let identity = ::create::new_identity::<f64>();
+
+    from_euler(&mut a, EulerOrder::intrinsic(EulerAxes::Zxy), 0.3f64, -0.2f64, 0.1f64);
+    ::transform::rotate(&mut b, &identity, -0.2f64, 0.1f64, 0.3f64);
+    assert_close(&a, &b, 1e-12f64);
+
+    from_euler(&mut a, EulerOrder::extrinsic(EulerAxes::Xyz), 0.3f64, -0.2f64, 0.1f64);
+    from_euler(&mut b, EulerOrder::intrinsic(EulerAxes::Zyx), 0.1f64, -0.2f64, 0.3f64);
+    assert_close(&a, &b, 0f64);
+
+    from_euler(&mut a, EulerOrder::intrinsic(EulerAxes::Xyz), 0f64, 0f64, 1f64);
+    ::transform::rotate_z(&mut b, &identity, 1f64);
+    assert_close(&a, &b, 1e-12f64);
+
+    // the same two quarter turns compose in opposite orders: Rx * Ry sends
+    // x to y, Ry * Rx sends x to -z
+    let half_pi = ::core::f64::consts::FRAC_PI_2;
+    from_euler(&mut a, EulerOrder::intrinsic(EulerAxes::Xyz), half_pi, half_pi, 0f64);
+    assert_close(&a, &[
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+    from_euler(&mut a, EulerOrder::extrinsic(EulerAxes::Xyz), half_pi, half_pi, 0f64);
+    assert_close(&a, &[
+        0f64, 0f64, -1f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        0f64, -1f64, 0f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+}
+
+/// Angles `[a, b, c]` about the axes of `order` that reproduce the rotation in
+/// the upper 3x3 of `m`, which must be orthonormal. The middle angle is in
+/// `[-pi/2, pi/2]` for Tait–Bryan orders and `[0, pi]` for proper Euler
+/// orders, the others in `[-pi, pi]`. In gimbal lock `c` is zero and `a`
+/// carries the whole remaining rotation.
+#[inline]
+pub fn to_euler<'b, T: Copy + Signed>(m: &'b [T; 16], order: EulerOrder) -> [T; 3] {
+    if order.extrinsic {
+        let angles = intrinsic_angles(m, order.axes.reversed(), true);
+        [angles[2], angles[1], angles[0]]
+    } else {
+        intrinsic_angles(m, order.axes, false)
+    }
+}
+
+// angles of `Ri(a) * Rj(b) * Rk(c)`; in gimbal lock either `a` or `c` is set
+// to zero depending on `zero_first`
+#[inline]
+fn intrinsic_angles<'b, T: Copy + Signed>(m: &'b [T; 16], axes: EulerAxes, zero_first: bool) -> [T; 3] {
+    let idx = axes.indices();
+    let i = idx[0];
+    let j = idx[1];
+    let k = 3 - i - j;
+    let r = |row: usize, col: usize| m[col * 4 + row];
+    // parity of (i, j, k) as a permutation of (x, y, z)
+    let s = if (j + 3 - i) % 3 == 1 { T::one() } else { -T::one() };
+    let lock = epsilon::<T>().sqrt();
+
+    let (a, b, c, locked) = if axes.is_proper() {
+        let sb = (r(i, j) * r(i, j) + r(i, k) * r(i, k)).sqrt();
+        let b = sb.atan2(r(i, i));
+        if sb > lock {
+            (r(j, i).atan2(-s * r(k, i)), b, r(i, j).atan2(s * r(i, k)), false)
+        } else {
+            ((s * r(k, j)).atan2(r(j, j)), b, T::zero(), true)
+        }
+    } else {
+        let cb = (r(i, i) * r(i, i) + r(i, j) * r(i, j)).sqrt();
+        let b = (s * r(i, k)).atan2(cb);
+        if cb > lock {
+            ((-s * r(j, k)).atan2(r(k, k)), b, (-s * r(i, j)).atan2(r(i, i)), false)
+        } else {
+            ((s * r(k, j)).atan2(r(j, j)), b, T::zero(), true)
+        }
+    };
+
+    if locked && zero_first {
+        // Ri(a) * Rj(b) == Rj(b) * Rl(+-a) where l is the last axis and the
+        // sign follows where Rj(-b) takes the first axis
+        let l = idx[2];
+        let v = axis_rotation(j, -b);
+        let sign = if v[l][i] < T::zero() { -T::one() } else { T::one() };
+        [T::zero(), b, sign * a]
+    } else {
+        [a, b, c]
+    }
+}
+#[test]
+fn test_to_euler() {
+    let all = [
+        EulerAxes::Xyz, EulerAxes::Xzy, EulerAxes::Yxz, EulerAxes::Yzx, EulerAxes::Zxy, EulerAxes::Zyx,
+        EulerAxes::Xyx, EulerAxes::Xzx, EulerAxes::Yxy, EulerAxes::Yzy, EulerAxes::Zxz, EulerAxes::Zyz,
+    ];
+    let half_pi = ::core::f64::consts::FRAC_PI_2;
+    let mut rng = Rng(41);
+    let mut m = [0f64; 16];
+    let mut n = [0f64; 16];
+
+    for axes in all.iter() {
+        for &extrinsic in [false, true].iter() {
+            let order = EulerOrder { axes: *axes, extrinsic };
+            for _ in 0..20 {
+                let q = rng.quat();
+                ::transform::from_quat(&mut m, &q);
+                let e = to_euler(&m, order);
+                from_euler(&mut n, order, e[0], e[1], e[2]);
+                assert_close(&m, &n, 1e-12f64);
+                if axes.is_proper() {
+                    assert!(e[1] >= 0f64);
+                } else {
+                    assert!(e[1].abs() <= half_pi);
+                }
+            }
+
+            // gimbal lock: the last angle is dropped and the rest still
+            // reproduces the rotation
+            let b = if axes.is_proper() { 0f64 } else { half_pi };
+            for &b in [b, -b + if axes.is_proper() { ::core::f64::consts::PI } else { 0f64 }].iter() {
+                from_euler(&mut m, order, 0.4f64, b, -0.7f64);
+                let e = to_euler(&m, order);
+                assert_eq!(e[2], 0f64);
+                from_euler(&mut n, order, e[0], e[1], e[2]);
+                assert_close(&m, &n, 1e-7f64);
+            }
+
+            let e = to_euler(&::create::new_identity::<f64>(), order);
+            assert_close(&e, &[0f64, 0f64, 0f64], 1e-12f64);
+        }
+    }
+
+    // a quarter turn about y alone is gimbal locked for XYZ
+    let y = [
+        0f64, 0f64, -1f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ];
+    let e = to_euler(&y, EulerOrder::intrinsic(EulerAxes::Xyz));
+    assert_close(&e, &[0f64, half_pi, 0f64], 1e-15f64);
+}
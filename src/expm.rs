@@ -0,0 +1,540 @@
+use num::Num;
+
+use create::new_identity;
+use misc::{abs, determinant, epsilon, is_rigid, max_abs, norm_inf, try_inverse};
+use mul::{mul, smul};
+use transform::to_axis_angle;
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+#[inline]
+fn add<T: Copy + Num>(a: &[T; 16], b: &[T; 16], s: T) -> [T; 16] {
+    let mut out = *a;
+    for i in 0..16 {
+        out[i] = a[i] + b[i] * s;
+    }
+    out
+}
+
+#[inline]
+fn is_proper_rigid<T: Copy + Num>(a: &[T; 16]) -> bool {
+    is_rigid(a, T::from_isize(16isize) * epsilon::<T>()) && determinant(a) > T::zero()
+}
+
+// a rigid motion's Lie algebra element: skew-symmetric upper 3x3 and a zero
+// bottom row
+#[inline]
+fn is_twist<T: Copy + Num>(a: &[T; 16]) -> bool {
+    let tol = T::from_isize(16isize) * epsilon::<T>() * max_abs(a);
+    let small = |x: T| abs(x) <= tol;
+    a[3] == T::zero() && a[7] == T::zero() && a[11] == T::zero() && a[15] == T::zero() &&
+    small(a[0]) && small(a[5]) && small(a[10]) &&
+    small(a[1] + a[4]) && small(a[2] + a[8]) && small(a[6] + a[9])
+}
+
+// `(sin t / t, (1 - cos t) / t^2, (t - sin t) / t^3)` for the rotation angle
+// `t`: the coefficients of `hat(w)` and `hat(w)^2` in the SO(3) exponential
+// and its left Jacobian, by series near zero
+#[inline]
+pub(crate) fn so3_coefficients<T: Copy + Num>(theta2: T) -> (T, T, T) {
+    let one = T::one();
+    if theta2 <= epsilon::<T>().sqrt() {
+        (
+            one - theta2 / T::from_isize(6isize),
+            T::from_f32(0.5f32) - theta2 / T::from_isize(24isize),
+            one / T::from_isize(6isize) - theta2 / T::from_isize(120isize),
+        )
+    } else {
+        let theta = theta2.sqrt();
+        let s = theta.sin();
+        (s / theta, (one - theta.cos()) / theta2, (theta - s) / (theta2 * theta))
+    }
+}
+
+// coefficient of `hat(w)^2` in the inverse SO(3) left Jacobian
+// `I - hat(w) / 2 + c hat(w)^2`
+#[inline]
+pub(crate) fn so3_inverse_coefficient<T: Copy + Num>(theta2: T) -> T {
+    let one = T::one();
+    if theta2 <= epsilon::<T>().sqrt() {
+        one / T::from_isize(12isize) + theta2 / T::from_isize(720isize)
+    } else {
+        let half = theta2.sqrt() * T::from_f32(0.5f32);
+        (one - half * half.cos() / half.sin()) / theta2
+    }
+}
+
+// `a + b * hat(w) + c * hat(w)^2` as a column major 3x3
+#[inline]
+pub(crate) fn rodrigues<T: Copy + Num>(out: &mut [T; 9], w: &[T; 3], a: T, b: T, c: T) {
+    let (x, y, z) = (w[0], w[1], w[2]);
+    out[0] = a - c * (y * y + z * z);
+    out[1] = b * z + c * x * y;
+    out[2] = c * x * z - b * y;
+    out[3] = c * x * y - b * z;
+    out[4] = a - c * (x * x + z * z);
+    out[5] = b * x + c * y * z;
+    out[6] = b * y + c * x * z;
+    out[7] = c * y * z - b * x;
+    out[8] = a - c * (x * x + y * y);
+}
+
+// writes the 3x3 `m` into the upper 3x3 of `out` and `m * t` into its
+// translation
+#[inline]
+fn set_motion<T: Copy + Num>(out: &mut [T; 16], m: &[T; 9], v: &[T; 9], t: &[T; 3]) {
+    for j in 0..3 {
+        for i in 0..3 {
+            out[j * 4 + i] = m[j * 3 + i];
+        }
+        out[j * 4 + 3] = T::zero();
+        out[12 + j] = v[j] * t[0] + v[3 + j] * t[1] + v[6 + j] * t[2];
+    }
+}
+
+// closed form exponential of a twist: Rodrigues for the rotation and the left
+// Jacobian applied to the translation
+fn twist_exp<T: Copy + Num>(out: &mut [T; 16], a: &[T; 16]) {
+    let w = [a[6], a[8], a[1]];
+    let (sa, sb, sc) = so3_coefficients(w[0] * w[0] + w[1] * w[1] + w[2] * w[2]);
+    let mut r = [T::zero(); 9];
+    let mut v = [T::zero(); 9];
+    rodrigues(&mut r, &w, T::one(), sa, sb);
+    rodrigues(&mut v, &w, T::one(), sb, sc);
+    set_motion(out, &r, &v, &[a[12], a[13], a[14]]);
+    out[15] = T::one();
+}
+
+// closed form logarithm of a proper rigid motion, the inverse of `twist_exp`
+fn rigid_log<T: Copy + Num>(out: &mut [T; 16], a: &[T; 16]) {
+    let mut axis = [T::zero(); 3];
+    let theta = to_axis_angle(a, &mut axis);
+    let w = [axis[0] * theta, axis[1] * theta, axis[2] * theta];
+    let mut h = [T::zero(); 9];
+    let mut v = [T::zero(); 9];
+    rodrigues(&mut h, &w, T::zero(), T::one(), T::zero());
+    rodrigues(&mut v, &w, T::one(), T::from_f32(-0.5f32), so3_inverse_coefficient(theta * theta));
+    set_motion(out, &h, &v, &[a[12], a[13], a[14]]);
+    out[15] = T::zero();
+}
+
+// Padé [6/6] with scaling and squaring
+fn exp_pade<T: Copy + Num>(out: &mut [T; 16], a: &[T; 16]) -> bool {
+    let norm = norm_inf(a);
+    let quarter = T::from_f32(0.25f32);
+    let mut squarings = 0;
+    let mut scale = T::one();
+    // no cap on the halvings: any finite norm drops below a quarter before
+    // `scale` runs out of exponent range, and an infinite one stops once
+    // `scale` reaches zero
+    while norm * scale > quarter {
+        scale = scale * T::from_f32(0.5f32);
+        squarings += 1;
+    }
+
+    let mut x = [T::zero(); 16];
+    smul(&mut x, a, scale);
+
+    let q = 6;
+    let mut c = T::one();
+    let mut power = new_identity::<T>();
+    let mut n = new_identity::<T>();
+    let mut d = new_identity::<T>();
+    let mut tmp = [T::zero(); 16];
+    for k in 1..(q + 1) {
+        c = c * T::from_isize((q - k + 1) as isize) / T::from_isize(((2 * q - k + 1) * k) as isize);
+        mul(&mut tmp, &power, &x);
+        power = tmp;
+        n = add(&n, &power, c);
+        d = add(&d, &power, if k % 2 == 0 {c} else {T::zero() - c});
+    }
+
+    let mut inv_d = [T::zero(); 16];
+    if try_inverse(&mut inv_d, &d).is_none() {
+        return false;
+    }
+    mul(out, &inv_d, &n);
+
+    for _ in 0..squarings {
+        tmp = *out;
+        mul(out, &tmp, &tmp);
+    }
+    true
+}
+
+// Denman–Beavers iteration
+fn sqrt_db<T: Copy + Num>(out: &mut [T; 16], a: &[T; 16]) -> bool {
+    let half = T::from_f32(0.5f32);
+    let tol = epsilon::<T>().sqrt();
+    let mut y = *a;
+    let mut z = new_identity::<T>();
+    let mut inv_y = [T::zero(); 16];
+    let mut inv_z = [T::zero(); 16];
+    let mut converged = false;
+
+    for _ in 0..64 {
+        if try_inverse(&mut inv_y, &y).is_none() || try_inverse(&mut inv_z, &z).is_none() {
+            return false;
+        }
+        let next = add(&y, &inv_z, T::one());
+        let mut diff = T::zero();
+        for i in 0..16 {
+            let v = next[i] * half;
+            diff = diff + (v - y[i]) * (v - y[i]);
+            y[i] = v;
+            z[i] = (z[i] + inv_y[i]) * half;
+        }
+        if converged {
+            *out = y;
+            return true;
+        }
+        converged = diff.sqrt() <= tol * norm_inf(&y);
+    }
+    false
+}
+
+// inverse scaling and squaring: square roots until close to identity, then the
+// series log(x) = 2 atanh((x - I)(x + I)^-1)
+fn log_general<T: Copy + Num>(out: &mut [T; 16], a: &[T; 16]) -> bool {
+    let identity = new_identity::<T>();
+    let mut x = *a;
+    let mut tmp = [T::zero(); 16];
+    let mut roots = 0;
+    while norm_inf(&add(&x, &identity, T::zero() - T::one())) > T::from_f32(0.25f32) {
+        if roots == 64 || !sqrt_db(&mut tmp, &x) {
+            return false;
+        }
+        x = tmp;
+        roots += 1;
+    }
+
+    let mut inv = [T::zero(); 16];
+    if try_inverse(&mut inv, &add(&x, &identity, T::one())).is_none() {
+        return false;
+    }
+    let mut z = [T::zero(); 16];
+    mul(&mut z, &add(&x, &identity, T::zero() - T::one()), &inv);
+    let mut z2 = [T::zero(); 16];
+    mul(&mut z2, &z, &z);
+
+    let mut sum = z;
+    let mut power = z;
+    for j in 1..16 {
+        mul(&mut tmp, &power, &z2);
+        power = tmp;
+        sum = add(&sum, &power, T::one() / T::from_isize((2 * j + 1) as isize));
+    }
+
+    let mut scale = T::from_isize(2isize);
+    for _ in 0..roots {
+        scale = scale * T::from_isize(2isize);
+    }
+    smul(out, &sum, scale);
+    true
+}
+
+/// Matrix exponential. Twists, matrices whose upper 3x3 is skew-symmetric and
+/// whose bottom row is zero, such as those returned by `log` for a rigid
+/// transform, take a closed form; everything else uses Padé approximation
+/// with scaling and squaring, which gives `None` when its denominator cannot
+/// be inverted.
+#[inline]
+pub fn exp<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> Option<&'a mut [T; 16]> {
+    if is_twist(a) {
+        twist_exp(out, a);
+        Some(out)
+    } else if exp_pade(out, a) {
+        Some(out)
+    } else {
+        None
+    }
+}
+#[test]
+fn test_exp() {
+    let mut rng = Rng(103);
+    let mut e = [0f64; 16];
+    let mut tmp = [0f64; 16];
+
+    assert!(exp(&mut e, &[0f64; 16]).is_some());
+    assert_close(&e, &new_identity::<f64>(), 0f64);
+    let mut d = [0f64; 16];
+    d[0] = 1f64;
+    d[5] = -2f64;
+    d[10] = 0.5f64;
+    d[15] = 3f64;
+    exp(&mut e, &d);
+    assert_close(&[e[0], e[5], e[10], e[15]], &[1f64.exp(), (-2f64).exp(), 0.5f64.exp(), 3f64.exp()], 1e-12f64 * 3f64.exp());
+
+    // far more halvings than a fixed cap would allow
+    let mut d = [0f64; 16];
+    d[0] = -1e30f64;
+    assert!(exp(&mut e, &d).is_some());
+    let mut expected = new_identity::<f64>();
+    expected[0] = 0f64;
+    assert_close(&e, &expected, 1e-12f64);
+
+    // against the Taylor series
+    for _ in 0..10 {
+        let mut a = rng.mat4();
+        for v in a.iter_mut() {
+            *v = *v * 1.5f64;
+        }
+        let mut sum = new_identity::<f64>();
+        let mut term = new_identity::<f64>();
+        for k in 1..60 {
+            mul(&mut tmp, &term, &a);
+            smul(&mut term, &tmp, 1f64 / k as f64);
+            sum = add(&sum, &term, 1f64);
+        }
+        exp(&mut e, &a);
+        assert_close(&e, &sum, 1e-12f64 * max_abs(&sum));
+    }
+
+    // the twist closed form agrees with Padé
+    for _ in 0..10 {
+        let m = rng.trs(true);
+        let mut twist = [0f64; 16];
+        rigid_log(&mut twist, &m);
+        assert!(is_twist(&twist));
+        exp(&mut e, &twist);
+        assert_close(&e, &m, 1e-12f64);
+        exp_pade(&mut tmp, &twist);
+        assert_close(&e, &tmp, 1e-12f64);
+    }
+
+    // nilpotent, so the series stops after the linear term: exp is I + a
+    let mut a = [0f64; 16];
+    a[4] = 1f64;
+    exp(&mut e, &a);
+    let mut expected = new_identity::<f64>();
+    expected[4] = 1f64;
+    assert_close(&e, &expected, 1e-15f64);
+}
+
+/// Principal matrix logarithm, or `None` if `a` is singular or has negative
+/// real eigenvalues. Proper rigid transforms take a closed form through their
+/// axis and angle and give a twist.
+#[inline]
+pub fn log<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> Option<&'a mut [T; 16]> {
+    if is_proper_rigid(a) {
+        rigid_log(out, a);
+        Some(out)
+    } else if log_general(out, a) {
+        Some(out)
+    } else {
+        None
+    }
+}
+#[test]
+fn test_log() {
+    let mut rng = Rng(107);
+    let mut e = [0f64; 16];
+    let mut l = [0f64; 16];
+    for _ in 0..10 {
+        let mut a = rng.mat4();
+        for v in a.iter_mut() {
+            *v = *v * 0.5f64;
+        }
+        exp(&mut e, &a);
+        assert!(log(&mut l, &e).is_some());
+        assert_close(&l, &a, 1e-10f64);
+
+        let m = rng.trs(true);
+        assert!(log(&mut l, &m).is_some());
+        let mut general = [0f64; 16];
+        assert!(log_general(&mut general, &m));
+        assert_close(&l, &general, 1e-10f64);
+        exp(&mut e, &l);
+        assert_close(&e, &m, 1e-12f64);
+    }
+
+    // a scale, which is not rigid and goes through the general path
+    let e1 = 1f64.exp();
+    let mut d = new_identity::<f64>();
+    d[0] = e1;
+    d[5] = e1 * e1;
+    assert!(log(&mut l, &d).is_some());
+    assert_close(&l, &[
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 2f64, 0f64, 0f64,
+        0f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, 0f64, 0f64
+    ], 1e-12f64);
+
+    // scaled, turned and far from the origin
+    let mut m = [0f64; 16];
+    ::transform::compose(&mut m, &[100f64, -100f64, 100f64], &[2f64, 2f64, 2f64], &rng.quat());
+    assert!(log(&mut l, &m).is_some());
+    exp(&mut e, &l);
+    assert_close(&e, &m, 1e-10f64);
+
+    let mut m = new_identity::<f32>();
+    m[0] = 2f32;
+    m[5] = 2f32;
+    m[10] = 2f32;
+    m[12] = 100f32;
+    m[13] = -100f32;
+    m[14] = 100f32;
+    let mut l32 = [0f32; 16];
+    assert!(log(&mut l32, &m).is_some());
+    let k = 2f32.ln();
+    let t = 100f32 * k;
+    let expected = [
+        k, 0f32, 0f32, 0f32,
+        0f32, k, 0f32, 0f32,
+        0f32, 0f32, k, 0f32,
+        t, -t, t, 0f32
+    ];
+    for (a, b) in l32.iter().zip(expected.iter()) {
+        assert!((a - b).abs() <= 1e-5f32 * 100f32);
+    }
+
+    let mut d = new_identity::<f64>();
+    d[0] = -1f64;
+    assert!(log(&mut l, &d).is_none());
+    assert!(log(&mut l, &[0f64; 16]).is_none());
+}
+
+/// Principal square root, or `None` under the same conditions as `log`.
+#[inline]
+pub fn sqrt<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> Option<&'a mut [T; 16]> {
+    if is_proper_rigid(a) {
+        powf(out, a, T::from_f32(0.5f32))
+    } else if sqrt_db(out, a) {
+        Some(out)
+    } else {
+        None
+    }
+}
+#[test]
+fn test_sqrt() {
+    let mut rng = Rng(109);
+    let mut s = [0f64; 16];
+    let mut p = [0f64; 16];
+    for _ in 0..10 {
+        let mut m = rng.mat4();
+        for i in 0..4 {
+            m[i * 5] += 3f64;
+        }
+        assert!(sqrt(&mut s, &m).is_some());
+        mul(&mut p, &s, &s);
+        assert_close(&p, &m, 1e-12f64);
+
+        let m = rng.trs(true);
+        assert!(sqrt(&mut s, &m).is_some());
+        assert!(is_rigid(&s, 1e-12f64));
+        mul(&mut p, &s, &s);
+        assert_close(&p, &m, 1e-12f64);
+    }
+
+    assert!(sqrt(&mut s, &[
+        4f64, 0f64, 0f64, 0f64,
+        0f64, 9f64, 0f64, 0f64,
+        0f64, 0f64, 16f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]).is_some());
+    assert_close(&s, &[
+        2f64, 0f64, 0f64, 0f64,
+        0f64, 3f64, 0f64, 0f64,
+        0f64, 0f64, 4f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-12f64);
+
+    // scaled, turned and far from the origin
+    let mut m = [0f64; 16];
+    ::transform::compose(&mut m, &[100f64, -100f64, 100f64], &[2f64, 2f64, 2f64], &rng.quat());
+    assert!(sqrt(&mut s, &m).is_some());
+    mul(&mut p, &s, &s);
+    assert_close(&p, &m, 1e-10f64);
+    let mut m = new_identity::<f64>();
+    m[12] = 1e5f64;
+    m[13] = 1e5f64;
+    m[14] = 1e5f64;
+    assert!(sqrt(&mut s, &m).is_some());
+    assert_close(&s[12..], &[5e4f64, 5e4f64, 5e4f64, 1f64], 1e-9f64);
+
+    let mut m = new_identity::<f32>();
+    m[0] = 2f32;
+    m[5] = 2f32;
+    m[10] = 2f32;
+    m[12] = 100f32;
+    m[13] = -100f32;
+    m[14] = 100f32;
+    let mut s32 = [0f32; 16];
+    assert!(sqrt(&mut s32, &m).is_some());
+    let r = 2f32.sqrt();
+    let t = 100f32 / (r + 1f32);
+    let expected = [
+        r, 0f32, 0f32, 0f32,
+        0f32, r, 0f32, 0f32,
+        0f32, 0f32, r, 0f32,
+        t, -t, t, 1f32
+    ];
+    for (a, b) in s32.iter().zip(expected.iter()) {
+        assert!((a - b).abs() <= 1e-5f32 * 100f32);
+    }
+
+    let mut d = new_identity::<f64>();
+    d[5] = -4f64;
+    assert!(sqrt(&mut s, &d).is_none());
+}
+
+/// `a` raised to the real power `t` as `exp(t * log(a))`, or `None` if `a`
+/// has no principal logarithm. Rigid transforms stay rigid, so `t = 0.5` is
+/// "half of this transform".
+#[inline]
+pub fn powf<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], t: T) -> Option<&'a mut [T; 16]> {
+    let mut l = [T::zero(); 16];
+    log(&mut l, a)?;
+    let mut tl = [T::zero(); 16];
+    smul(&mut tl, &l, t);
+    exp(out, &tl)
+}
+#[test]
+fn test_powf() {
+    let mut rng = Rng(113);
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    let mut p = [0f64; 16];
+    for _ in 0..10 {
+        let mut m = rng.mat4();
+        for i in 0..4 {
+            m[i * 5] += 3f64;
+        }
+        powf(&mut a, &m, 2f64);
+        mul(&mut p, &m, &m);
+        assert_close(&a, &p, 1e-10f64 * max_abs(&p));
+
+        powf(&mut a, &m, 1f64 / 3f64);
+        mul(&mut b, &a, &a);
+        mul(&mut p, &b, &a);
+        assert_close(&p, &m, 1e-10f64);
+
+        powf(&mut a, &m, 0.5f64);
+        sqrt(&mut b, &m);
+        assert_close(&a, &b, 1e-10f64);
+
+        // a rigid transform split in two halves
+        let m = rng.trs(true);
+        powf(&mut a, &m, 0.5f64);
+        assert!(is_rigid(&a, 1e-12f64));
+        mul(&mut p, &a, &a);
+        assert_close(&p, &m, 1e-12f64);
+        powf(&mut a, &m, 0f64);
+        assert_close(&a, &new_identity::<f64>(), 1e-12f64);
+    }
+
+    // a pure translation has no rotation axis to speak of; half of it is
+    // half the distance
+    let mut m = new_identity::<f64>();
+    m[12] = 2f64;
+    m[13] = 4f64;
+    m[14] = 6f64;
+    powf(&mut a, &m, 0.5f64);
+    let mut expected = new_identity::<f64>();
+    expected[12] = 1f64;
+    expected[13] = 2f64;
+    expected[14] = 3f64;
+    assert_close(&a, &expected, 1e-15f64);
+}
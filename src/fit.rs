@@ -0,0 +1,511 @@
+use num::Num;
+
+use decomposition::svd3;
+use lu::Lu4;
+use xorshift::below;
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+// whether the inputs line up, so that indexing by point is safe
+fn matches<T>(from: &[[T; 3]], to: &[[T; 3]], weights: Option<&[T]>) -> bool {
+    from.len() == to.len() && match weights {
+        Some(w) => w.len() == from.len(),
+        None => true,
+    }
+}
+
+// weighted centroids of both point sets and the total weight, or `None` when
+// the total weight is not positive
+fn centroids<T: Copy + Num, W: Fn(usize) -> T>(from: &[[T; 3]], to: &[[T; 3]], weight: &W) -> Option<([T; 3], [T; 3], T)> {
+    let mut cx = [T::zero(); 3];
+    let mut cy = [T::zero(); 3];
+    let mut total = T::zero();
+    for i in 0..from.len() {
+        let w = weight(i);
+        for k in 0..3 {
+            cx[k] = cx[k] + w * from[i][k];
+            cy[k] = cy[k] + w * to[i][k];
+        }
+        total = total + w;
+    }
+    if total <= T::zero() {
+        return None;
+    }
+    for k in 0..3 {
+        cx[k] = cx[k] / total;
+        cy[k] = cy[k] / total;
+    }
+    Some((cx, cy, total))
+}
+
+// squared distance between `m * p` and `q`
+#[inline]
+fn residual2<T: Copy + Num>(m: &[T; 16], p: &[T; 3], q: &[T; 3]) -> T {
+    let mut sum = T::zero();
+    for k in 0..3 {
+        let d = m[k] * p[0] + m[4 + k] * p[1] + m[8 + k] * p[2] + m[12 + k] - q[k];
+        sum = sum + d * d;
+    }
+    sum
+}
+
+// weighted RMS distance between `m * from` and `to`
+fn rms<T: Copy + Num, W: Fn(usize) -> T>(m: &[T; 16], from: &[[T; 3]], to: &[[T; 3]], weight: &W, total: T) -> T {
+    let mut sum = T::zero();
+    for i in 0..from.len() {
+        sum = sum + weight(i) * residual2(m, &from[i], &to[i]);
+    }
+    (sum / total).sqrt()
+}
+
+// Kabsch and Umeyama share everything but the scale
+fn fit_orthogonal<T: Copy + Num, W: Fn(usize) -> T>(
+    out: &mut [T; 16], from: &[[T; 3]], to: &[[T; 3]], weight: &W, allow_reflection: bool, with_scale: bool
+) -> Option<T> {
+    let (cx, cy, total) = centroids(from, to, weight)?;
+
+    // cross covariance of the centred sets, to * from^T
+    let mut h = [T::zero(); 16];
+    let mut var = T::zero();
+    for i in 0..from.len() {
+        let w = weight(i);
+        let x = [from[i][0] - cx[0], from[i][1] - cx[1], from[i][2] - cx[2]];
+        let y = [to[i][0] - cy[0], to[i][1] - cy[1], to[i][2] - cy[2]];
+        for c in 0..3 {
+            for r in 0..3 {
+                h[c * 4 + r] = h[c * 4 + r] + w * y[r] * x[c];
+            }
+            var = var + w * x[c] * x[c];
+        }
+    }
+
+    let mut u = [T::zero(); 16];
+    let mut v = [T::zero(); 16];
+    let mut sigma = [T::zero(); 3];
+    svd3(&mut u, &mut sigma, &mut v, &h);
+
+    let det = |m: &[T; 16]| {
+        m[0] * (m[5] * m[10] - m[9] * m[6]) - m[4] * (m[1] * m[10] - m[9] * m[2]) + m[8] * (m[1] * m[6] - m[5] * m[2])
+    };
+    let flip = !allow_reflection && (det(&u) < T::zero()) != (det(&v) < T::zero());
+    let d = if flip {T::zero() - T::one()} else {T::one()};
+
+    let scale = if with_scale {
+        if var == T::zero() {
+            return None;
+        }
+        (sigma[0] + sigma[1] + d * sigma[2]) / var
+    } else {
+        T::one()
+    };
+
+    for j in 0..3 {
+        for i in 0..3 {
+            let r = u[i] * v[j] + u[4 + i] * v[4 + j] + d * u[8 + i] * v[8 + j];
+            out[j * 4 + i] = scale * r;
+        }
+        out[j * 4 + 3] = T::zero();
+    }
+    for i in 0..3 {
+        out[12 + i] = cy[i] - (out[i] * cx[0] + out[4 + i] * cx[1] + out[8 + i] * cx[2]);
+    }
+    out[15] = T::one();
+
+    Some(rms(out, from, to, weight, total))
+}
+
+/// Rigid transform best mapping `from` onto `to` in the weighted least squares
+/// sense (Kabsch). Returns the RMS residual, or `None` for mismatched or
+/// empty input or a total weight of zero. Unless `allow_reflection` is set
+/// the rotation is proper even when a mirror image would fit better.
+#[inline]
+pub fn fit_rigid<'b, T: Copy + Num>(
+    out: &mut [T; 16], from: &'b [[T; 3]], to: &'b [[T; 3]], weights: Option<&'b [T]>, allow_reflection: bool
+) -> Option<T> {
+    if !matches(from, to, weights) {
+        return None;
+    }
+    fit_orthogonal(out, from, to, &|i| weights.map_or(T::one(), |w| w[i]), allow_reflection, false)
+}
+#[cfg(test)]
+fn random_points(rng: &mut Rng, count: usize) -> [[f64; 3]; 16] {
+    let mut points = [[0f64; 3]; 16];
+    for p in points[..count].iter_mut() {
+        *p = [rng.next() * 10f64, rng.next() * 10f64, rng.next() * 10f64];
+    }
+    points
+}
+#[cfg(test)]
+fn apply(m: &[f64; 16], points: &[[f64; 3]]) -> [[f64; 3]; 16] {
+    let mut out = [[0f64; 3]; 16];
+    for (o, p) in out.iter_mut().zip(points.iter()) {
+        ::point::transform_point3(o, m, p);
+    }
+    out
+}
+#[test]
+fn test_fit_rigid() {
+    let mut rng = Rng(157);
+    let mut m = [0f64; 16];
+    for _ in 0..10 {
+        let expected = rng.trs(true);
+        let from = random_points(&mut rng, 16);
+        let mut to = apply(&expected, &from);
+        let rms = fit_rigid(&mut m, &from, &to, None, false).unwrap();
+        assert!(rms < 1e-12f64);
+        assert_close(&m, &expected, 1e-12f64);
+
+        // a zero weight ignores an outlier
+        to[3] = [100f64, 0f64, 0f64];
+        let mut weights = [1f64; 16];
+        weights[3] = 0f64;
+        assert!(fit_rigid(&mut m, &from, &to, Some(&weights), false).unwrap() < 1e-12f64);
+        assert_close(&m, &expected, 1e-12f64);
+        assert!(fit_rigid(&mut m, &from, &to, None, false).unwrap() > 1f64);
+    }
+
+    // a mirror image is rejected unless allowed
+    let from = random_points(&mut rng, 8);
+    let mut to = from;
+    for p in to.iter_mut() {
+        p[2] = -p[2];
+    }
+    assert!(fit_rigid(&mut m, &from[..8], &to[..8], None, false).unwrap() > 1e-3f64);
+    assert!(::misc::determinant(&m) > 0f64);
+    assert!(fit_rigid(&mut m, &from[..8], &to[..8], None, true).unwrap() < 1e-12f64);
+    assert!(::misc::determinant(&m) < 0f64);
+
+    // a half turn about z: the covariance is symmetric, with no skew part to
+    // read the rotation from
+    let from = [[1f64, 0f64, 0f64], [-1f64, 0f64, 0f64], [0f64, 1f64, 0f64]];
+    assert!(fit_rigid(&mut m, &from, &[[-1f64, 0f64, 0f64], [1f64, 0f64, 0f64], [0f64, -1f64, 0f64]], None, false).unwrap() < 1e-15f64);
+    assert_close(&m, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, -1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+
+    // the ends of a segment twisted about its midpoint can at best be
+    // rotated onto the twisted line, leaving each sqrt(2) - 1 off
+    let rms = fit_rigid(&mut m, &[[0f64, 0f64, 0f64], [2f64, 0f64, 0f64]], &[[0f64, 1f64, 0f64], [2f64, -1f64, 0f64]], None, false).unwrap();
+    assert!((rms - (2f64.sqrt() - 1f64)).abs() < 1e-15f64);
+
+    assert!(fit_rigid(&mut m, &from[..3], &to[..2], None, false).is_none());
+    assert!(fit_rigid(&mut m, &from[..2], &to[..2], Some(&[0f64, 0f64]), false).is_none());
+    assert!(fit_rigid::<f64>(&mut m, &[], &[], None, false).is_none());
+}
+
+/// Similarity transform, rotation with uniform scale and translation, best
+/// mapping `from` onto `to` (Umeyama). As `fit_rigid`, and also `None` when
+/// all weighted `from` points coincide.
+#[inline]
+pub fn fit_similarity<'b, T: Copy + Num>(
+    out: &mut [T; 16], from: &'b [[T; 3]], to: &'b [[T; 3]], weights: Option<&'b [T]>, allow_reflection: bool
+) -> Option<T> {
+    if !matches(from, to, weights) {
+        return None;
+    }
+    fit_orthogonal(out, from, to, &|i| weights.map_or(T::one(), |w| w[i]), allow_reflection, true)
+}
+#[test]
+fn test_fit_similarity() {
+    let mut rng = Rng(163);
+    let mut m = [0f64; 16];
+    let mut expected = [0f64; 16];
+    for _ in 0..10 {
+        let s = rng.next() + 2f64;
+        ::transform::compose(&mut expected, &[rng.next(), rng.next(), rng.next()], &[s, s, s], &rng.quat());
+        let from = random_points(&mut rng, 16);
+        let to = apply(&expected, &from);
+        let weights = [0.5f64, 1f64, 2f64, 1f64, 0.5f64, 1f64, 2f64, 1f64, 0.5f64, 1f64, 2f64, 1f64, 0.5f64, 1f64, 2f64, 1f64];
+        assert!(fit_similarity(&mut m, &from, &to, Some(&weights), false).unwrap() < 1e-11f64);
+        assert_close(&m, &expected, 1e-12f64);
+    }
+
+    // doubled about the origin, then moved to (1, 1, 1): the scale must come
+    // from the spread of `from`, not of `to`
+    let from = [[0f64, 0f64, 0f64], [1f64, 0f64, 0f64], [0f64, 1f64, 0f64], [0f64, 0f64, 1f64]];
+    let to = [[1f64, 1f64, 1f64], [3f64, 1f64, 1f64], [1f64, 3f64, 1f64], [1f64, 1f64, 3f64]];
+    assert!(fit_similarity(&mut m, &from, &to, None, false).unwrap() < 1e-15f64);
+    assert_close(&m, &[
+        2f64, 0f64, 0f64, 0f64,
+        0f64, 2f64, 0f64, 0f64,
+        0f64, 0f64, 2f64, 0f64,
+        1f64, 1f64, 1f64, 1f64
+    ], 1e-15f64);
+
+    let from = [[1f64, 2f64, 3f64]; 4];
+    assert!(fit_similarity(&mut m, &from, &from, None, false).is_none());
+}
+
+/// General affine transform best mapping `from` onto `to` by weighted least
+/// squares. Returns the RMS residual, or `None` for mismatched input or when
+/// the weighted `from` points are coplanar.
+#[inline]
+pub fn fit_affine<'b, T: Copy + Num>(out: &mut [T; 16], from: &'b [[T; 3]], to: &'b [[T; 3]], weights: Option<&'b [T]>) -> Option<T> {
+    if !matches(from, to, weights) {
+        return None;
+    }
+    let weight = |i| weights.map_or(T::one(), |w: &[T]| w[i]);
+    let (cx, cy, total) = centroids(from, to, &weight)?;
+
+    // normal equations of the centred sets: n * A^T = b
+    let mut n = [T::zero(); 16];
+    let mut b = [T::zero(); 16];
+    b[15] = T::one();
+    for i in 0..from.len() {
+        let w = weight(i);
+        let x = [from[i][0] - cx[0], from[i][1] - cx[1], from[i][2] - cx[2]];
+        let y = [to[i][0] - cy[0], to[i][1] - cy[1], to[i][2] - cy[2]];
+        for c in 0..3 {
+            for r in 0..3 {
+                n[c * 4 + r] = n[c * 4 + r] + w * x[r] * x[c];
+                b[c * 4 + r] = b[c * 4 + r] + w * x[r] * y[c];
+            }
+        }
+    }
+
+    // padding with the trace keeps the rank tolerance relative to the 3x3
+    // block, however small the coordinates
+    n[15] = n[0] + n[5] + n[10];
+    let lu = Lu4::new(&n);
+    if lu.rank() < 4 {
+        return None;
+    }
+    let mut at = [T::zero(); 16];
+    lu.solve_mat(&mut at, &b)?;
+
+    for j in 0..3 {
+        for i in 0..3 {
+            out[j * 4 + i] = at[i * 4 + j];
+        }
+        out[j * 4 + 3] = T::zero();
+    }
+    for i in 0..3 {
+        out[12 + i] = cy[i] - (out[i] * cx[0] + out[4 + i] * cx[1] + out[8 + i] * cx[2]);
+    }
+    out[15] = T::one();
+
+    Some(rms(out, from, to, &weight, total))
+}
+#[test]
+fn test_fit_affine() {
+    let mut rng = Rng(167);
+    let mut m = [0f64; 16];
+    for _ in 0..10 {
+        let mut expected = rng.mat4();
+        expected[3] = 0f64;
+        expected[7] = 0f64;
+        expected[11] = 0f64;
+        expected[15] = 1f64;
+        let from = random_points(&mut rng, 16);
+        let to = apply(&expected, &from);
+        assert!(fit_affine(&mut m, &from, &to, None).unwrap() < 1e-11f64);
+        assert_close(&m, &expected, 1e-11f64);
+    }
+
+    // a shear of y along x, which no similarity can produce
+    let from = [[0f64, 0f64, 0f64], [1f64, 0f64, 0f64], [0f64, 1f64, 0f64], [0f64, 0f64, 1f64]];
+    let to = [[0f64, 0f64, 0f64], [1f64, 0f64, 0f64], [1f64, 1f64, 0f64], [0f64, 0f64, 1f64]];
+    assert!(fit_affine(&mut m, &from, &to, None).unwrap() < 1e-15f64);
+    assert_close(&m, &[
+        1f64, 0f64, 0f64, 0f64,
+        1f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+
+    // tiny but well spread coordinates are not mistaken for a plane
+    let mut expected = rng.mat4();
+    expected[3] = 0f64;
+    expected[7] = 0f64;
+    expected[11] = 0f64;
+    expected[12] = 0f64;
+    expected[13] = 0f64;
+    expected[14] = 0f64;
+    expected[15] = 1f64;
+    let mut from = random_points(&mut rng, 16);
+    for p in from.iter_mut() {
+        for x in p.iter_mut() {
+            *x *= 1e-9f64;
+        }
+    }
+    let to = apply(&expected, &from);
+    assert!(fit_affine(&mut m, &from, &to, None).is_some());
+    assert_close(&m, &expected, 1e-9f64);
+
+    let mut from = random_points(&mut rng, 16);
+    for p in from.iter_mut() {
+        p[1] = 2f64 * p[0] - p[2];
+    }
+    assert!(fit_affine(&mut m, &from, &from, None).is_none());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FitModel {
+    Rigid,
+    Similarity,
+}
+
+/// Settings for `ransac`. `threshold` is the largest distance between `m *
+/// from[i]` and `to[i]` for which a correspondence counts as an inlier.
+#[derive(Clone, Copy, Debug)]
+pub struct Ransac<T> {
+    pub model: FitModel,
+    pub threshold: T,
+    pub iterations: usize,
+    pub seed: u32,
+    pub allow_reflection: bool,
+}
+
+impl<T> Ransac<T> {
+    #[inline]
+    pub fn new(model: FitModel, threshold: T) -> Self {
+        Ransac {
+            model,
+            threshold,
+            iterations: 256,
+            seed: 1,
+            allow_reflection: false,
+        }
+    }
+}
+
+// number of inliers of `m` and the sum of their squared residuals
+fn score<T: Copy + Num>(m: &[T; 16], from: &[[T; 3]], to: &[[T; 3]], threshold2: T) -> (usize, T) {
+    let mut count = 0;
+    let mut error = T::zero();
+    for i in 0..from.len() {
+        let r = residual2(m, &from[i], &to[i]);
+        if r <= threshold2 {
+            count += 1;
+            error = error + r;
+        }
+    }
+    (count, error)
+}
+
+// writes the inliers of `m` into `mask`, returning whether any changed
+fn mark<T: Copy + Num>(mask: &mut [bool], m: &[T; 16], from: &[[T; 3]], to: &[[T; 3]], threshold2: T) -> bool {
+    let mut changed = false;
+    for i in 0..from.len() {
+        let inlier = residual2(m, &from[i], &to[i]) <= threshold2;
+        changed |= mask[i] != inlier;
+        mask[i] = inlier;
+    }
+    changed
+}
+
+/// Robust rigid or similarity fit of `from` onto `to`. Each iteration fits a
+/// random sample of three correspondences and scores it by its inliers, ties
+/// going to the smaller residual; the best model is then refit on its inliers
+/// until the inlier set settles. Writes the final inliers into `inliers` and
+/// returns the RMS residual over them, or `None` for mismatched input, fewer
+/// than three correspondences or no usable sample. Results are deterministic
+/// for a given `seed`.
+pub fn ransac<'b, T: Copy + Num>(
+    out: &mut [T; 16], inliers: &mut [bool], from: &'b [[T; 3]], to: &'b [[T; 3]], options: &Ransac<T>
+) -> Option<T> {
+    let n = from.len();
+    if n < 3 || to.len() != n || inliers.len() != n {
+        return None;
+    }
+    let with_scale = options.model == FitModel::Similarity;
+    let threshold2 = options.threshold * options.threshold;
+    let mut state = if options.seed == 0 {0x9e37_79b9} else {options.seed};
+
+    let mut best = None;
+    let mut m = [T::zero(); 16];
+    for _ in 0..options.iterations {
+        let a = below(&mut state, n);
+        let mut b = below(&mut state, n);
+        while b == a {
+            b = below(&mut state, n);
+        }
+        let mut c = below(&mut state, n);
+        while c == a || c == b {
+            c = below(&mut state, n);
+        }
+
+        let sample_from = [from[a], from[b], from[c]];
+        let sample_to = [to[a], to[b], to[c]];
+        if fit_orthogonal(&mut m, &sample_from, &sample_to, &|_| T::one(), options.allow_reflection, with_scale).is_none() {
+            continue;
+        }
+        let (count, error) = score(&m, from, to, threshold2);
+        let better = match best {
+            Some((best_count, best_error, _)) => count > best_count || (count == best_count && error < best_error),
+            None => true,
+        };
+        if better {
+            best = Some((count, error, m));
+            if count == n {
+                break;
+            }
+        }
+    }
+
+    let (_, _, m) = best?;
+    mark(inliers, &m, from, to, threshold2);
+    for _ in 0..8 {
+        let rms = fit_orthogonal(out, from, to, &|i| if inliers[i] {T::one()} else {T::zero()}, options.allow_reflection, with_scale)?;
+        if !mark(inliers, out, from, to, threshold2) {
+            return Some(rms);
+        }
+    }
+
+    // still moving: report `out` over the inliers it was just marked with
+    let count = inliers.iter().filter(|&&inlier| inlier).count();
+    if count == 0 {
+        return None;
+    }
+    Some(rms(out, from, to, &|i| if inliers[i] {T::one()} else {T::zero()}, T::from_isize(count as isize)))
+}
+#[test]
+fn test_ransac() {
+    let mut rng = Rng(173);
+    let mut m = [0f64; 16];
+    let mut inliers = [false; 16];
+    let outliers = [2usize, 5, 6, 11, 15];
+    for &model in [FitModel::Rigid, FitModel::Similarity].iter() {
+        for _ in 0..5 {
+            let expected = if model == FitModel::Rigid {
+                rng.trs(true)
+            } else {
+                let s = rng.next() + 2f64;
+                let mut out = [0f64; 16];
+                ::transform::compose(&mut out, &[rng.next(), rng.next(), rng.next()], &[s, s, s], &rng.quat());
+                out
+            };
+            let from = random_points(&mut rng, 16);
+            let mut to = apply(&expected, &from);
+            for &i in outliers.iter() {
+                to[i] = [to[i][0] + 5f64 + rng.next(), to[i][1] - 5f64, to[i][2]];
+            }
+            // a plain least squares fit is wrecked by the outliers
+            assert!(fit_similarity(&mut m, &from, &to, None, false).unwrap() > 1f64);
+
+            let options = Ransac::new(model, 1e-6f64);
+            let rms = ransac(&mut m, &mut inliers, &from, &to, &options).unwrap();
+            assert!(rms < 1e-12f64);
+            assert_close(&m, &expected, 1e-12f64);
+            for (i, &inlier) in inliers.iter().enumerate() {
+                assert_eq!(inlier, !outliers.contains(&i));
+            }
+
+            // the same seed reproduces the same result
+            let mut again = [0f64; 16];
+            let mut again_inliers = [false; 16];
+            ransac(&mut again, &mut again_inliers, &from, &to, &options);
+            assert_eq!(again, m);
+            assert_eq!(again_inliers, inliers);
+        }
+    }
+
+    let from = random_points(&mut rng, 4);
+    let options = Ransac::new(FitModel::Rigid, 1e-6f64);
+    assert!(ransac(&mut m, &mut inliers[..2], &from[..2], &from[..2], &options).is_none());
+    assert!(ransac(&mut m, &mut inliers[..4], &from[..4], &from[..3], &options).is_none());
+}
@@ -0,0 +1,215 @@
+use num::Num;
+use vec3;
+
+use misc::inverse_rigid;
+use mul::mul;
+use transform::{decompose_full, from_quat, recompose, to_quat};
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+#[inline]
+fn lerp<T: Copy + Num>(a: T, b: T, t: T) -> T {
+    a + (b - a) * t
+}
+
+#[inline]
+pub fn slerp<'a, 'b, T: Copy + Num>(out: &'a mut [T; 4], a: &'b [T; 4], b: &'b [T; 4], t: T) -> &'a mut [T; 4] {
+    let mut bx = b[0];
+    let mut by = b[1];
+    let mut bz = b[2];
+    let mut bw = b[3];
+    let mut cosom = a[0] * bx + a[1] * by + a[2] * bz + a[3] * bw;
+
+    if cosom < T::zero() {
+        cosom = T::zero() - cosom;
+        bx = T::zero() - bx;
+        by = T::zero() - by;
+        bz = T::zero() - bz;
+        bw = T::zero() - bw;
+    }
+
+    let (scale0, scale1) = if T::one() - cosom > T::from_f32(0.000001f32) {
+        let omega = cosom.acos();
+        let sinom = omega.sin();
+        (((T::one() - t) * omega).sin() / sinom, (t * omega).sin() / sinom)
+    } else {
+        (T::one() - t, t)
+    };
+
+    out[0] = scale0 * a[0] + scale1 * bx;
+    out[1] = scale0 * a[1] + scale1 * by;
+    out[2] = scale0 * a[2] + scale1 * bz;
+    out[3] = scale0 * a[3] + scale1 * bw;
+
+    let len = (out[0] * out[0] + out[1] * out[1] + out[2] * out[2] + out[3] * out[3]).sqrt();
+    if len != T::zero() {
+        let inv_len = T::one() / len;
+        out[0] = out[0] * inv_len;
+        out[1] = out[1] * inv_len;
+        out[2] = out[2] * inv_len;
+        out[3] = out[3] * inv_len;
+    }
+    out
+}
+#[test]
+fn test_slerp() {
+    let h = 0.5f64.sqrt();
+    let mut q = [0f64; 4];
+    slerp(&mut q, &[0f64, 0f64, 0f64, 1f64], &[0f64, 0f64, 1f64, 0f64], 0.5f64);
+    assert_close(&q, &[0f64, 0f64, h, h], 1e-12f64);
+    slerp(&mut q, &[0f64, 0f64, 0f64, 1f64], &[0f64, 0f64, -h, -h], 0.5f64);
+    let s = (0.125f64 * ::core::f64::consts::PI).sin();
+    let c = (0.125f64 * ::core::f64::consts::PI).cos();
+    assert_close(&q, &[0f64, 0f64, s, c], 1e-12f64);
+}
+
+/// Interpolates between `a` and `b` by decomposing both with `decompose_full`,
+/// lerping translation, scale, shear and perspective, slerping the rotation
+/// along the shortest path and recomposing. Returns `None` and leaves `out`
+/// untouched if either matrix cannot be decomposed.
+#[inline]
+pub fn interpolate<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], b: &'b [T; 16], t: T) -> Option<&'a mut [T; 16]> {
+    let mut at = [T::zero(); 3];
+    let mut a_scale = [T::zero(); 3];
+    let mut a_shear = [T::zero(); 3];
+    let mut ar = [T::zero(); 4];
+    let mut ap = [T::zero(); 4];
+    let mut bt = [T::zero(); 3];
+    let mut b_scale = [T::zero(); 3];
+    let mut b_shear = [T::zero(); 3];
+    let mut br = [T::zero(); 4];
+    let mut bp = [T::zero(); 4];
+
+    if !decompose_full(a, &mut at, &mut a_scale, &mut a_shear, &mut ar, &mut ap) ||
+       !decompose_full(b, &mut bt, &mut b_scale, &mut b_shear, &mut br, &mut bp) {
+        return None;
+    }
+
+    for i in 0..3 {
+        at[i] = lerp(at[i], bt[i], t);
+        a_scale[i] = lerp(a_scale[i], b_scale[i], t);
+        a_shear[i] = lerp(a_shear[i], b_shear[i], t);
+    }
+    for i in 0..4 {
+        ap[i] = lerp(ap[i], bp[i], t);
+    }
+    let mut rotation = [T::zero(); 4];
+    slerp(&mut rotation, &ar, &br, t);
+
+    Some(recompose(out, &at, &a_scale, &a_shear, &rotation, &ap))
+}
+#[test]
+fn test_interpolate() {
+    let mut rng = Rng(11);
+    let mut m = [0f64; 16];
+    for _ in 0..20 {
+        let a = rng.mat4();
+        let b = rng.mat4();
+        assert!(interpolate(&mut m, &a, &b, 0f64).is_some());
+        assert_close(&m, &a, 1e-9f64);
+        assert!(interpolate(&mut m, &a, &b, 1f64).is_some());
+        assert_close(&m, &b, 1e-9f64);
+    }
+
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    let mut c = [0f64; 16];
+    let h = 0.5f64.sqrt();
+    ::transform::compose(&mut a, &[0f64, 0f64, 0f64], &[1f64, 1f64, 1f64], &[0f64, 0f64, 0f64, 1f64]);
+    ::transform::compose(&mut b, &[2f64, 4f64, 6f64], &[3f64, 3f64, 3f64], &[0f64, 0f64, 1f64, 0f64]);
+    ::transform::compose(&mut c, &[1f64, 2f64, 3f64], &[2f64, 2f64, 2f64], &[0f64, 0f64, h, h]);
+    interpolate(&mut m, &a, &b, 0.5f64);
+    assert_close(&m, &c, 1e-9f64);
+
+    b[8] = 0f64;
+    b[9] = 0f64;
+    b[10] = 0f64;
+    assert!(interpolate(&mut m, &a, &b, 0.5f64).is_none());
+}
+
+/// Interpolates between the rigid transforms `a` and `b` along the screw
+/// motion taking one to the other, equivalent to dual quaternion ScLERP.
+#[inline]
+pub fn interpolate_rigid<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], b: &'b [T; 16], t: T) -> &'a mut [T; 16] {
+    let mut inv_a = [T::zero(); 16];
+    let mut d = [T::zero(); 16];
+    inverse_rigid(&mut inv_a, a);
+    mul(&mut d, &inv_a, b);
+
+    let mut q = [T::zero(); 4];
+    to_quat(&mut q, &d, false);
+    if q[3] < T::zero() {
+        q = [T::zero() - q[0], T::zero() - q[1], T::zero() - q[2], T::zero() - q[3]];
+    }
+
+    let dx = d[12];
+    let dy = d[13];
+    let dz = d[14];
+    let half = T::from_f32(0.5f32);
+    let sin_half = vec3::length_values(q[0], q[1], q[2]);
+
+    let mut dt = [T::zero(); 16];
+    if sin_half == T::zero() {
+        from_quat(&mut dt, &[T::zero(), T::zero(), T::zero(), T::one()]);
+        dt[12] = dx * t;
+        dt[13] = dy * t;
+        dt[14] = dz * t;
+    } else {
+        let ux = q[0] / sin_half;
+        let uy = q[1] / sin_half;
+        let uz = q[2] / sin_half;
+        let theta_half = sin_half.atan2(q[3]);
+        let phi_half = theta_half * t;
+        let sin_phi_half = phi_half.sin();
+        let cos_phi_half = phi_half.cos();
+        let sin_phi = T::from_isize(2isize) * sin_phi_half * cos_phi_half;
+        let one_minus_cos_phi = T::from_isize(2isize) * sin_phi_half * sin_phi_half;
+        let cot_half = q[3] / sin_half;
+
+        // split d into the slide along the screw axis and the part swept by
+        // rotating about the axis, which passes through a point c with
+        // (I - R) c = d_perp
+        let along = ux * dx + uy * dy + uz * dz;
+        let px = dx - ux * along;
+        let py = dy - uy * along;
+        let pz = dz - uz * along;
+        let wx = uy * pz - uz * py;
+        let wy = uz * px - ux * pz;
+        let wz = ux * py - uy * px;
+        let alpha = half * (one_minus_cos_phi + cot_half * sin_phi);
+        let beta = half * (cot_half * one_minus_cos_phi - sin_phi);
+
+        from_quat(&mut dt, &[ux * sin_phi_half, uy * sin_phi_half, uz * sin_phi_half, cos_phi_half]);
+        dt[12] = alpha * px + beta * wx + ux * along * t;
+        dt[13] = alpha * py + beta * wy + uy * along * t;
+        dt[14] = alpha * pz + beta * wz + uz * along * t;
+    }
+
+    mul(out, a, &dt)
+}
+#[test]
+fn test_interpolate_rigid() {
+    let mut rng = Rng(5);
+    let mut m = [0f64; 16];
+    for _ in 0..20 {
+        let a = rng.trs(true);
+        let b = rng.trs(true);
+        interpolate_rigid(&mut m, &a, &b, 0f64);
+        assert_close(&m, &a, 1e-9f64);
+        interpolate_rigid(&mut m, &a, &b, 1f64);
+        assert_close(&m, &b, 1e-9f64);
+    }
+
+    // a quarter turn about the z axis through (1, 0, 0) is halfway an eighth
+    // turn about the same axis
+    let h = 0.5f64.sqrt();
+    let mut b = [0f64; 16];
+    let mut c = [0f64; 16];
+    ::transform::compose(&mut b, &[1f64, -1f64, 2f64], &[1f64, 1f64, 1f64], &[0f64, 0f64, h, h]);
+    let s = (0.125f64 * ::core::f64::consts::PI).sin();
+    let co = (0.125f64 * ::core::f64::consts::PI).cos();
+    ::transform::compose(&mut c, &[1f64 - h, -h, 1f64], &[1f64, 1f64, 1f64], &[0f64, 0f64, s, co]);
+    interpolate_rigid(&mut m, &::create::new_identity(), &b, 0.5f64);
+    assert_close(&m, &c, 1e-9f64);
+}
@@ -1,6 +1,10 @@
 #![no_std]
 
 
+#[cfg(feature = "std")]
+#[macro_use]
+extern crate std;
+
 extern crate approx_eq;
 extern crate num;
 extern crate signed;
@@ -22,5 +26,52 @@ pub use sdiv::*;
 pub mod misc;
 pub use misc::*;
 
+pub mod clip;
+pub use clip::*;
+
 pub mod transform;
 pub use transform::*;
+
+pub mod euler;
+pub use euler::*;
+
+pub mod lu;
+pub use lu::*;
+
+pub mod decomposition;
+pub use decomposition::*;
+
+pub mod se3;
+pub use se3::*;
+
+pub mod expm;
+pub use expm::*;
+
+pub mod fit;
+pub use fit::*;
+
+pub mod point;
+pub use point::*;
+
+pub mod batch;
+pub use batch::*;
+
+pub mod culling;
+pub use culling::*;
+
+pub mod screen;
+pub use screen::*;
+
+pub mod interpolate;
+pub use interpolate::*;
+
+pub mod simd;
+pub use simd::*;
+
+pub mod mat4;
+pub use mat4::*;
+
+mod xorshift;
+
+#[cfg(test)]
+mod test_util;
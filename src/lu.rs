@@ -0,0 +1,234 @@
+use num::Num;
+
+use misc::{abs, epsilon, max_abs};
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+/// LU factorization `P * m = L * U` with partial pivoting, reusable across
+/// solves. `L` (unit diagonal, below it) and `U` share one column major array.
+#[derive(Clone, Copy, Debug)]
+pub struct Lu4<T> {
+    lu: [T; 16],
+    rows: [usize; 4],
+    odd: bool,
+    scale: T,
+}
+
+impl<T: Copy + Num> Lu4<T> {
+    pub fn new(m: &[T; 16]) -> Self {
+        let mut lu = *m;
+        let mut rows = [0usize, 1, 2, 3];
+        let mut odd = false;
+
+        for k in 0..4 {
+            let mut p = k;
+            for i in (k + 1)..4 {
+                if abs(lu[k * 4 + i]) > abs(lu[k * 4 + p]) {
+                    p = i;
+                }
+            }
+            if p != k {
+                for j in 0..4 {
+                    lu.swap(j * 4 + k, j * 4 + p);
+                }
+                rows.swap(k, p);
+                odd = !odd;
+            }
+
+            let pivot = lu[k * 4 + k];
+            if pivot == T::zero() {
+                // nothing to eliminate below a zero column
+                continue;
+            }
+            for i in (k + 1)..4 {
+                let l = lu[k * 4 + i] / pivot;
+                lu[k * 4 + i] = l;
+                for j in (k + 1)..4 {
+                    lu[j * 4 + i] = lu[j * 4 + i] - l * lu[j * 4 + k];
+                }
+            }
+        }
+
+        Lu4 {
+            lu,
+            rows,
+            odd,
+            scale: max_abs(m),
+        }
+    }
+
+    /// Whether a pivot is exactly zero, so that `solve` has no unique answer.
+    #[inline]
+    pub fn is_singular(&self) -> bool {
+        (0..4).any(|k| self.lu[k * 5] == T::zero())
+    }
+
+    #[inline]
+    pub fn determinant(&self) -> T {
+        let d = self.lu[0] * self.lu[5] * self.lu[10] * self.lu[15];
+        if self.odd {T::zero() - d} else {d}
+    }
+
+    /// Estimated rank: the number of pivots larger than `4 * epsilon` relative
+    /// to the largest entry of the factored matrix, or the number of non-zero
+    /// pivots for integer `T`. Partial pivoting only searches down each column,
+    /// so a column that is already zero below the diagonal hides the rest of
+    /// its row and the estimate can fall short; use `decomposition::svd` when
+    /// the rank has to be exact.
+    #[inline]
+    pub fn rank(&self) -> usize {
+        let e = epsilon::<T>();
+        let tol = if e < T::one() {T::from_isize(4isize) * e * self.scale} else {T::zero()};
+        (0..4).filter(|&k| abs(self.lu[k * 5]) > tol).count()
+    }
+
+    /// Solves `m * x = b`, or returns `None` if `m` is singular.
+    #[inline]
+    pub fn solve<'a, 'b>(&self, out: &'a mut [T; 4], b: &'b [T; 4]) -> Option<&'a mut [T; 4]> {
+        if self.is_singular() {
+            return None;
+        }
+        let lu = &self.lu;
+        let mut z = [T::zero(); 4];
+        for i in 0..4 {
+            let mut sum = b[self.rows[i]];
+            for j in 0..i {
+                sum = sum - lu[j * 4 + i] * z[j];
+            }
+            z[i] = sum;
+        }
+        for i in (0..4).rev() {
+            let mut sum = z[i];
+            for j in (i + 1)..4 {
+                sum = sum - lu[j * 4 + i] * z[j];
+            }
+            z[i] = sum / lu[i * 5];
+        }
+        *out = z;
+        Some(out)
+    }
+
+    /// Solves `m * x = b` for every column of `b`.
+    #[inline]
+    pub fn solve_mat<'a, 'b>(&self, out: &'a mut [T; 16], b: &'b [T; 16]) -> Option<&'a mut [T; 16]> {
+        if self.is_singular() {
+            return None;
+        }
+        for j in 0..4 {
+            let mut x = [T::zero(); 4];
+            self.solve(&mut x, &[b[j * 4], b[j * 4 + 1], b[j * 4 + 2], b[j * 4 + 3]]);
+            out[j * 4] = x[0];
+            out[j * 4 + 1] = x[1];
+            out[j * 4 + 2] = x[2];
+            out[j * 4 + 3] = x[3];
+        }
+        Some(out)
+    }
+
+    #[inline]
+    pub fn inverse<'a>(&self, out: &'a mut [T; 16]) -> Option<&'a mut [T; 16]> {
+        let mut identity = [T::zero(); 16];
+        ::set::identity(&mut identity);
+        self.solve_mat(out, &identity)
+    }
+}
+#[test]
+fn test_lu4() {
+    let mut rng = Rng(151);
+    let mut x = [0f64; 4];
+    let mut inv = [0f64; 16];
+    for _ in 0..20 {
+        let m = rng.mat4();
+        let lu = Lu4::new(&m);
+        assert!(!lu.is_singular());
+        assert_eq!(lu.rank(), 4);
+        assert!((lu.determinant() - ::misc::determinant(&m)).abs() < 1e-12f64);
+
+        let b = [rng.next(), rng.next(), rng.next(), rng.next()];
+        assert!(lu.solve(&mut x, &b).is_some());
+        let mut mx = [0f64; 4];
+        for i in 0..4 {
+            mx[i] = m[i] * x[0] + m[4 + i] * x[1] + m[8 + i] * x[2] + m[12 + i] * x[3];
+        }
+        assert_close(&mx, &b, 1e-10f64);
+
+        assert!(lu.inverse(&mut inv).is_some());
+        let mut expected = [0f64; 16];
+        ::misc::inverse(&mut expected, &m);
+        assert_close(&inv, &expected, 1e-9f64 * ::misc::max_abs(&expected));
+    }
+
+    let mut rank2 = rng.mat4();
+    for k in 0..4 {
+        rank2[8 + k] = rank2[k] + rank2[4 + k];
+        rank2[12 + k] = 2f64 * rank2[4 + k];
+    }
+    let lu = Lu4::new(&rank2);
+    assert_eq!(lu.rank(), 2);
+    assert!(lu.determinant().abs() < 1e-12f64);
+
+    let lu = Lu4::new(&[0f64; 16]);
+    assert!(lu.is_singular());
+    assert_eq!(lu.rank(), 0);
+    assert!(lu.solve(&mut x, &[1f64, 0f64, 0f64, 0f64]).is_none());
+
+    // needs a row swap
+    let lu = Lu4::new(&[
+        0f64, 1f64, 0f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]);
+    assert_eq!(lu.determinant(), -1f64);
+    lu.solve(&mut x, &[1f64, 2f64, 3f64, 4f64]);
+    assert_eq!(x, [2f64, 1f64, 3f64, 4f64]);
+
+    // the only non-zero entry lies right of a zero column, where partial
+    // pivoting never looks, so the rank falls short
+    let mut single = [0f64; 16];
+    single[4] = 1f64;
+    let lu = Lu4::new(&single);
+    assert!(lu.is_singular());
+    assert_eq!(lu.rank(), 0);
+    assert_eq!(lu.determinant(), 0f64);
+
+    // integers count every non-zero pivot
+    let mut m = [0i32; 16];
+    ::set::identity(&mut m);
+    assert_eq!(Lu4::new(&m).rank(), 4);
+    m[0] = 0;
+    m[4] = 2;
+    m[1] = 3;
+    let lu = Lu4::new(&m);
+    assert_eq!(lu.rank(), 4);
+    assert_eq!(lu.determinant(), -6);
+    m[4] = 0;
+    assert_eq!(Lu4::new(&m).rank(), 3);
+}
+
+/// Solves `m * x = b` by LU factorization with partial pivoting, or returns
+/// `None` if `m` is singular. Factor once with `Lu4::new` to reuse `m` across
+/// right-hand sides.
+#[inline]
+pub fn solve<'a, 'b, T: Copy + Num>(out: &'a mut [T; 4], m: &'b [T; 16], b: &'b [T; 4]) -> Option<&'a mut [T; 4]> {
+    Lu4::new(m).solve(out, b)
+}
+#[test]
+fn test_solve() {
+    // Hilbert matrix: badly conditioned, yet the residual stays small
+    let mut m = [0f64; 16];
+    for j in 0..4 {
+        for i in 0..4 {
+            m[j * 4 + i] = 1f64 / (i + j + 1) as f64;
+        }
+    }
+    let expected = [1f64, -2f64, 3f64, -4f64];
+    let mut b = [0f64; 4];
+    for i in 0..4 {
+        b[i] = m[i] * expected[0] + m[4 + i] * expected[1] + m[8 + i] * expected[2] + m[12 + i] * expected[3];
+    }
+    let mut x = [0f64; 4];
+    assert!(solve(&mut x, &m, &b).is_some());
+    assert_close(&x, &expected, 1e-10f64);
+}
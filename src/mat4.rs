@@ -0,0 +1,700 @@
+use core::ops::{Div, Index, IndexMut, Mul, MulAssign};
+
+use approx_eq::ApproxEq;
+use num::Num;
+use signed::Signed;
+
+use clip::ClipSpace;
+use create;
+use decomposition;
+use euler::{self, EulerOrder};
+use expm;
+use fit::{self, Ransac};
+use interpolate;
+use lu::{self, Lu4};
+use misc;
+use mul;
+use point;
+use sdiv;
+use se3;
+use set;
+use simd::{self, Kernel};
+use transform::{self, Axis};
+
+
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct Mat4<T>(pub [T; 16]);
+
+impl<T: Copy + Num> Mat4<T> {
+    #[inline]
+    pub fn new(
+        m11: T, m12: T, m13: T, m14: T,
+        m21: T, m22: T, m23: T, m24: T,
+        m31: T, m32: T, m33: T, m34: T,
+        m41: T, m42: T, m43: T, m44: T
+    ) -> Self {
+        Mat4(create::new(
+            m11, m12, m13, m14,
+            m21, m22, m23, m24,
+            m31, m32, m33, m34,
+            m41, m42, m43, m44
+        ))
+    }
+    #[inline]
+    pub fn identity() -> Self { Mat4(create::new_identity()) }
+    #[inline]
+    pub fn zero() -> Self { Mat4(create::new_zero()) }
+
+    #[inline]
+    pub fn from_mat2(m: &[T; 4]) -> Self {
+        let mut out = Self::identity();
+        set::from_mat2(&mut out.0, m);
+        out
+    }
+    #[inline]
+    pub fn from_mat32(m: &[T; 6]) -> Self {
+        let mut out = Self::identity();
+        set::from_mat32(&mut out.0, m);
+        out
+    }
+    #[inline]
+    pub fn from_mat3(m: &[T; 9]) -> Self {
+        let mut out = Self::identity();
+        set::from_mat3(&mut out.0, m);
+        out
+    }
+    #[inline]
+    pub fn from_quat(q: &[T; 4]) -> Self {
+        let mut out = Self::identity();
+        transform::from_quat(&mut out.0, q);
+        out
+    }
+    #[inline]
+    pub fn from_axis_angle(axis: &[T; 3], angle: T) -> Self {
+        let mut out = Self::identity();
+        transform::from_axis_angle(&mut out.0, axis, angle);
+        out
+    }
+    #[inline]
+    pub fn from_basis(x: &[T; 3], y: &[T; 3], z: &[T; 3]) -> Self {
+        let mut out = Self::identity();
+        transform::from_basis(&mut out.0, x, y, z);
+        out
+    }
+    #[inline]
+    pub fn to_quat(&self, normalize: bool) -> [T; 4] {
+        let mut out = [T::zero(); 4];
+        transform::to_quat(&mut out, &self.0, normalize);
+        out
+    }
+    #[inline]
+    pub fn to_axis_angle(&self) -> ([T; 3], T) {
+        let mut axis = [T::zero(); 3];
+        let angle = transform::to_axis_angle(&self.0, &mut axis);
+        (axis, angle)
+    }
+    #[inline]
+    pub fn to_basis(&self) -> ([T; 3], [T; 3], [T; 3]) {
+        ([self.0[0], self.0[1], self.0[2]], [self.0[4], self.0[5], self.0[6]], [self.0[8], self.0[9], self.0[10]])
+    }
+    #[inline]
+    pub fn compose(position: &[T; 3], scale: &[T; 3], rotation: &[T; 4]) -> Self {
+        let mut out = Self::identity();
+        transform::compose(&mut out.0, position, scale, rotation);
+        out
+    }
+
+    #[inline]
+    pub fn set(
+        &mut self,
+        m11: T, m12: T, m13: T, m14: T,
+        m21: T, m22: T, m23: T, m24: T,
+        m31: T, m32: T, m33: T, m34: T,
+        m41: T, m42: T, m43: T, m44: T
+    ) -> &mut Self {
+        set::set(
+            &mut self.0,
+            m11, m12, m13, m14,
+            m21, m22, m23, m24,
+            m31, m32, m33, m34,
+            m41, m42, m43, m44
+        );
+        self
+    }
+    #[inline]
+    pub fn set_identity(&mut self) -> &mut Self {
+        set::identity(&mut self.0);
+        self
+    }
+    #[inline]
+    pub fn set_zero(&mut self) -> &mut Self {
+        set::zero(&mut self.0);
+        self
+    }
+    #[inline]
+    pub fn set_position(&mut self, v: &[T; 3]) -> &mut Self {
+        transform::set_position(&mut self.0, v);
+        self
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> &[T; 16] { &self.0 }
+    #[inline]
+    pub fn as_mut_array(&mut self) -> &mut [T; 16] { &mut self.0 }
+    #[inline]
+    pub fn into_array(self) -> [T; 16] { self.0 }
+
+    #[inline]
+    pub fn inverse_affine(&self) -> Self {
+        let mut out = *self;
+        misc::inverse_affine(&mut out.0, &self.0);
+        out
+    }
+    #[inline]
+    pub fn inverse_rigid(&self) -> Self {
+        let mut out = *self;
+        misc::inverse_rigid(&mut out.0, &self.0);
+        out
+    }
+    #[inline]
+    pub fn inverse_auto(&self) -> Self {
+        let mut out = *self;
+        misc::inverse_auto(&mut out.0, &self.0);
+        out
+    }
+    #[inline]
+    pub fn try_inverse(&self) -> Option<Self> {
+        let mut out = *self;
+        misc::try_inverse(&mut out.0, &self.0).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn try_inverse_eps(&self, eps: T) -> Option<Self> {
+        let mut out = *self;
+        misc::try_inverse_eps(&mut out.0, &self.0, eps).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn condition(&self) -> Option<T> { misc::condition(&self.0) }
+    #[inline]
+    pub fn determinant(&self) -> T { misc::determinant(&self.0) }
+    #[inline]
+    pub fn is_orthonormal(&self, eps: T) -> bool { misc::is_orthonormal(&self.0, eps) }
+    #[inline]
+    pub fn lu(&self) -> Lu4<T> { Lu4::new(&self.0) }
+    #[inline]
+    pub fn solve(&self, b: &[T; 4]) -> Option<[T; 4]> {
+        let mut out = [T::zero(); 4];
+        lu::solve(&mut out, &self.0, b).map(|x| *x)
+    }
+    #[inline]
+    pub fn fit_rigid(from: &[[T; 3]], to: &[[T; 3]], weights: Option<&[T]>, allow_reflection: bool) -> Option<(Self, T)> {
+        let mut out = Self::identity();
+        fit::fit_rigid(&mut out.0, from, to, weights, allow_reflection).map(|rms| (out, rms))
+    }
+    #[inline]
+    pub fn fit_similarity(from: &[[T; 3]], to: &[[T; 3]], weights: Option<&[T]>, allow_reflection: bool) -> Option<(Self, T)> {
+        let mut out = Self::identity();
+        fit::fit_similarity(&mut out.0, from, to, weights, allow_reflection).map(|rms| (out, rms))
+    }
+    #[inline]
+    pub fn fit_affine(from: &[[T; 3]], to: &[[T; 3]], weights: Option<&[T]>) -> Option<(Self, T)> {
+        let mut out = Self::identity();
+        fit::fit_affine(&mut out.0, from, to, weights).map(|rms| (out, rms))
+    }
+    #[inline]
+    pub fn ransac(inliers: &mut [bool], from: &[[T; 3]], to: &[[T; 3]], options: &Ransac<T>) -> Option<(Self, T)> {
+        let mut out = Self::identity();
+        fit::ransac(&mut out.0, inliers, from, to, options).map(|rms| (out, rms))
+    }
+    #[inline]
+    pub fn svd(&self) -> (Self, [T; 4], Self) {
+        let mut u = Self::identity();
+        let mut sigma = [T::zero(); 4];
+        let mut v = Self::identity();
+        decomposition::svd(&mut u.0, &mut sigma, &mut v.0, &self.0);
+        (u, sigma, v)
+    }
+    #[inline]
+    pub fn svd3(&self) -> (Self, [T; 3], Self) {
+        let mut u = Self::identity();
+        let mut sigma = [T::zero(); 3];
+        let mut v = Self::identity();
+        decomposition::svd3(&mut u.0, &mut sigma, &mut v.0, &self.0);
+        (u, sigma, v)
+    }
+    #[inline]
+    pub fn se3_exp(xi: &[T; 6]) -> Self {
+        let mut out = Self::identity();
+        se3::se3_exp(&mut out.0, xi);
+        out
+    }
+    #[inline]
+    pub fn se3_log(&self) -> [T; 6] {
+        let mut out = [T::zero(); 6];
+        se3::se3_log(&mut out, &self.0);
+        out
+    }
+    #[inline]
+    pub fn adjoint(&self) -> [T; 36] {
+        let mut out = [T::zero(); 36];
+        se3::adjoint(&mut out, &self.0);
+        out
+    }
+    #[inline]
+    pub fn exp(&self) -> Option<Self> {
+        let mut out = *self;
+        expm::exp(&mut out.0, &self.0).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn log(&self) -> Option<Self> {
+        let mut out = *self;
+        expm::log(&mut out.0, &self.0).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn sqrt(&self) -> Option<Self> {
+        let mut out = *self;
+        expm::sqrt(&mut out.0, &self.0).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn powf(&self, t: T) -> Option<Self> {
+        let mut out = *self;
+        expm::powf(&mut out.0, &self.0, t).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn eigen_symmetric(&self) -> ([T; 4], Self) {
+        let mut values = [T::zero(); 4];
+        let mut vectors = Self::identity();
+        decomposition::eigen_symmetric(&mut vectors.0, &mut values, &self.0);
+        (values, vectors)
+    }
+    #[inline]
+    pub fn polar(&self) -> (Self, Self) {
+        let mut r = Self::identity();
+        let mut s = Self::identity();
+        decomposition::polar(&mut r.0, &mut s.0, &self.0);
+        (r, s)
+    }
+    #[inline]
+    pub fn polar3(&self) -> (Self, Self) {
+        let mut r = Self::identity();
+        let mut s = Self::identity();
+        decomposition::polar3(&mut r.0, &mut s.0, &self.0);
+        (r, s)
+    }
+    #[inline]
+    pub fn qr(&self) -> (Self, Self) {
+        let mut q = Self::identity();
+        let mut r = Self::identity();
+        decomposition::qr(&mut q.0, &mut r.0, &self.0);
+        (q, r)
+    }
+    #[inline]
+    pub fn qr3(&self) -> (Self, Self) {
+        let mut q = Self::identity();
+        let mut r = Self::identity();
+        decomposition::qr3(&mut q.0, &mut r.0, &self.0);
+        (q, r)
+    }
+
+    #[inline]
+    pub fn decompose(&self, position: &mut [T; 3], scale: &mut [T; 3], rotation: &mut [T; 4]) {
+        transform::decompose(&self.0, position, scale, rotation)
+    }
+    #[inline]
+    pub fn decompose_full(
+        &self,
+        translation: &mut [T; 3],
+        scale: &mut [T; 3],
+        shear: &mut [T; 3],
+        rotation: &mut [T; 4],
+        perspective: &mut [T; 4]
+    ) -> bool {
+        transform::decompose_full(&self.0, translation, scale, shear, rotation, perspective)
+    }
+    #[inline]
+    pub fn recompose(
+        translation: &[T; 3],
+        scale: &[T; 3],
+        shear: &[T; 3],
+        rotation: &[T; 4],
+        perspective: &[T; 4]
+    ) -> Self {
+        let mut out = Self::identity();
+        transform::recompose(&mut out.0, translation, scale, shear, rotation, perspective);
+        out
+    }
+    #[inline]
+    pub fn interpolate(&self, b: &Self, t: T) -> Option<Self> {
+        let mut out = *self;
+        interpolate::interpolate(&mut out.0, &self.0, &b.0, t).map(|m| Mat4(*m))
+    }
+    #[inline]
+    pub fn interpolate_rigid(&self, b: &Self, t: T) -> Self {
+        let mut out = *self;
+        interpolate::interpolate_rigid(&mut out.0, &self.0, &b.0, t);
+        out
+    }
+    #[inline]
+    pub fn transform_vec4(&self, v: &[T; 4]) -> [T; 4] {
+        let mut out = *v;
+        point::transform_vec4(&mut out, &self.0, v);
+        out
+    }
+    #[inline]
+    pub fn transform_point3(&self, p: &[T; 3]) -> [T; 3] {
+        let mut out = *p;
+        point::transform_point3(&mut out, &self.0, p);
+        out
+    }
+    #[inline]
+    pub fn transform_vector3(&self, v: &[T; 3]) -> [T; 3] {
+        let mut out = *v;
+        point::transform_vector3(&mut out, &self.0, v);
+        out
+    }
+    #[inline]
+    pub fn transform_normal(&self, n: &[T; 3]) -> [T; 3] {
+        let mut out = *n;
+        point::transform_normal(&mut out, &self.0, n);
+        out
+    }
+    #[inline]
+    pub fn project_point3(&self, p: &[T; 3]) -> Option<[T; 3]> {
+        let mut out = *p;
+        point::project_point3(&mut out, &self.0, p).map(|x| *x)
+    }
+    #[inline]
+    pub fn position(&self) -> [T; 3] { [self.0[12], self.0[13], self.0[14]] }
+    #[inline]
+    pub fn orthonormalize(&self, primary: Axis) -> Self {
+        let mut out = *self;
+        transform::orthonormalize(&mut out.0, &self.0, primary);
+        out
+    }
+    #[inline]
+    pub fn nearest_rotation(&self) -> Self {
+        let mut out = *self;
+        transform::nearest_rotation(&mut out.0, &self.0);
+        out
+    }
+    #[inline]
+    pub fn extract_scale(&self) -> [T; 3] {
+        let mut out = [T::zero(); 3];
+        transform::extract_scale(&mut out, &self.0);
+        out
+    }
+    #[inline]
+    pub fn extract_rotation(&self) -> Self {
+        let mut out = *self;
+        transform::extract_rotation(&mut out.0, &self.0);
+        out
+    }
+    #[inline]
+    pub fn translate(&self, v: &[T; 3]) -> Self {
+        let mut out = *self;
+        transform::translate(&mut out.0, &self.0, v);
+        out
+    }
+    #[inline]
+    pub fn scale(&self, v: &[T; 3]) -> Self {
+        let mut out = *self;
+        transform::scale(&mut out.0, &self.0, v);
+        out
+    }
+    #[inline]
+    pub fn rotate_x(&self, angle: T) -> Self {
+        let mut out = *self;
+        transform::rotate_x(&mut out.0, &self.0, angle);
+        out
+    }
+    #[inline]
+    pub fn rotate_y(&self, angle: T) -> Self {
+        let mut out = *self;
+        transform::rotate_y(&mut out.0, &self.0, angle);
+        out
+    }
+    #[inline]
+    pub fn rotate_z(&self, angle: T) -> Self {
+        let mut out = *self;
+        transform::rotate_z(&mut out.0, &self.0, angle);
+        out
+    }
+    #[inline]
+    pub fn rotate(&self, x: T, y: T, z: T) -> Self {
+        let mut out = *self;
+        transform::rotate(&mut out.0, &self.0, x, y, z);
+        out
+    }
+    #[inline]
+    pub fn rotate_axis(&self, axis: &[T; 3], angle: T) -> Self {
+        let mut out = *self;
+        transform::rotate_axis(&mut out.0, &self.0, axis, angle);
+        out
+    }
+}
+
+impl<T: Kernel> Mat4<T> {
+    #[inline]
+    pub fn inverse(&self) -> Self {
+        let mut out = *self;
+        simd::inverse_simd(&mut out.0, &self.0);
+        out
+    }
+    #[inline]
+    pub fn transpose(&self) -> Self {
+        let mut out = *self;
+        simd::transpose_simd(&mut out.0, &self.0);
+        out
+    }
+}
+
+impl<T: Copy + Signed> Mat4<T> {
+    #[inline]
+    pub fn from_euler(order: EulerOrder, a: T, b: T, c: T) -> Self {
+        let mut out = Self::identity();
+        euler::from_euler(&mut out.0, order, a, b, c);
+        out
+    }
+    #[inline]
+    pub fn to_euler(&self, order: EulerOrder) -> [T; 3] {
+        euler::to_euler(&self.0, order)
+    }
+    #[inline]
+    pub fn from_rotation_arc(from: &[T; 3], to: &[T; 3]) -> Self {
+        let mut out = Self::identity();
+        transform::from_rotation_arc(&mut out.0, from, to);
+        out
+    }
+    #[inline]
+    pub fn look_at(eye: &[T; 3], target: &[T; 3], up: &[T; 3]) -> Self {
+        let mut out = Self::identity();
+        transform::look_at(&mut out.0, eye, target, up);
+        out
+    }
+    #[inline]
+    pub fn look_at_lh(eye: &[T; 3], target: &[T; 3], up: &[T; 3]) -> Self {
+        let mut out = Self::identity();
+        transform::look_at_lh(&mut out.0, eye, target, up);
+        out
+    }
+    #[inline]
+    pub fn look_to(eye: &[T; 3], direction: &[T; 3], up: &[T; 3]) -> Self {
+        let mut out = Self::identity();
+        transform::look_to(&mut out.0, eye, direction, up);
+        out
+    }
+    #[inline]
+    pub fn look_to_lh(eye: &[T; 3], direction: &[T; 3], up: &[T; 3]) -> Self {
+        let mut out = Self::identity();
+        transform::look_to_lh(&mut out.0, eye, direction, up);
+        out
+    }
+    #[inline]
+    pub fn look_at_continuous(eye: &[T; 3], target: &[T; 3], up: &[T; 3], previous: &Self) -> Self {
+        let mut out = Self::identity();
+        transform::look_at_continuous(&mut out.0, eye, target, up, &previous.0);
+        out
+    }
+    #[inline]
+    pub fn look_to_continuous(eye: &[T; 3], direction: &[T; 3], up: &[T; 3], previous: &Self) -> Self {
+        let mut out = Self::identity();
+        transform::look_to_continuous(&mut out.0, eye, direction, up, &previous.0);
+        out
+    }
+    #[inline]
+    pub fn try_look_at(eye: &[T; 3], target: &[T; 3], up: &[T; 3]) -> Result<Self, transform::LookAtError> {
+        let mut out = Self::identity();
+        transform::try_look_at(&mut out.0, eye, target, up)?;
+        Ok(out)
+    }
+    #[inline]
+    pub fn try_look_to(eye: &[T; 3], direction: &[T; 3], up: &[T; 3]) -> Result<Self, transform::LookAtError> {
+        let mut out = Self::identity();
+        transform::try_look_to(&mut out.0, eye, direction, up)?;
+        Ok(out)
+    }
+    #[inline]
+    pub fn frustum(top: T, right: T, bottom: T, left: T, near: T, far: T) -> Self {
+        let mut out = Self::identity();
+        transform::frustum(&mut out.0, top, right, bottom, left, near, far);
+        out
+    }
+    #[inline]
+    pub fn perspective(fov: T, aspect: T, near: T, far: T) -> Self {
+        let mut out = Self::identity();
+        transform::perspective(&mut out.0, fov, aspect, near, far);
+        out
+    }
+    #[inline]
+    pub fn orthographic(top: T, right: T, bottom: T, left: T, near: T, far: T) -> Self {
+        let mut out = Self::identity();
+        transform::orthographic(&mut out.0, top, right, bottom, left, near, far);
+        out
+    }
+    #[inline]
+    pub fn frustum_lh(top: T, right: T, bottom: T, left: T, near: T, far: T) -> Self {
+        let mut out = Self::identity();
+        transform::frustum_lh(&mut out.0, top, right, bottom, left, near, far);
+        out
+    }
+    #[inline]
+    pub fn perspective_lh(fov: T, aspect: T, near: T, far: T) -> Self {
+        let mut out = Self::identity();
+        transform::perspective_lh(&mut out.0, fov, aspect, near, far);
+        out
+    }
+    #[inline]
+    pub fn orthographic_lh(top: T, right: T, bottom: T, left: T, near: T, far: T) -> Self {
+        let mut out = Self::identity();
+        transform::orthographic_lh(&mut out.0, top, right, bottom, left, near, far);
+        out
+    }
+    #[inline]
+    pub fn frustum_clip(top: T, right: T, bottom: T, left: T, near: T, far: T, clip: &ClipSpace) -> Self {
+        let mut out = Self::identity();
+        transform::frustum_clip(&mut out.0, top, right, bottom, left, near, far, clip);
+        out
+    }
+    #[inline]
+    pub fn perspective_clip(fov: T, aspect: T, near: T, far: T, clip: &ClipSpace) -> Self {
+        let mut out = Self::identity();
+        transform::perspective_clip(&mut out.0, fov, aspect, near, far, clip);
+        out
+    }
+    #[inline]
+    pub fn orthographic_clip(top: T, right: T, bottom: T, left: T, near: T, far: T, clip: &ClipSpace) -> Self {
+        let mut out = Self::identity();
+        transform::orthographic_clip(&mut out.0, top, right, bottom, left, near, far, clip);
+        out
+    }
+}
+
+impl<T: Copy + Num> Default for Mat4<T> {
+    #[inline]
+    fn default() -> Self { Self::identity() }
+}
+
+impl<T: Copy + Num> From<[T; 16]> for Mat4<T> {
+    #[inline]
+    fn from(m: [T; 16]) -> Self { Mat4(m) }
+}
+impl<T: Copy + Num> From<Mat4<T>> for [T; 16] {
+    #[inline]
+    fn from(m: Mat4<T>) -> Self { m.0 }
+}
+
+impl<T: Copy + Num> AsRef<[T; 16]> for Mat4<T> {
+    #[inline]
+    fn as_ref(&self) -> &[T; 16] { &self.0 }
+}
+impl<T: Copy + Num> AsMut<[T; 16]> for Mat4<T> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut [T; 16] { &mut self.0 }
+}
+
+impl<T: Copy + Num> Index<(usize, usize)> for Mat4<T> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, (row, col): (usize, usize)) -> &T {
+        &self.0[col * 4 + row]
+    }
+}
+impl<T: Copy + Num> IndexMut<(usize, usize)> for Mat4<T> {
+    #[inline]
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut T {
+        &mut self.0[col * 4 + row]
+    }
+}
+
+impl<T: Kernel> Mul<Mat4<T>> for Mat4<T> {
+    type Output = Mat4<T>;
+
+    #[inline]
+    fn mul(self, b: Mat4<T>) -> Mat4<T> {
+        let mut out = self;
+        simd::mul_simd(&mut out.0, &self.0, &b.0);
+        out
+    }
+}
+impl<'a, T: Kernel> Mul<&'a Mat4<T>> for &'a Mat4<T> {
+    type Output = Mat4<T>;
+
+    #[inline]
+    fn mul(self, b: &'a Mat4<T>) -> Mat4<T> {
+        let mut out = *self;
+        simd::mul_simd(&mut out.0, &self.0, &b.0);
+        out
+    }
+}
+impl<T: Copy + Num> Mul<T> for Mat4<T> {
+    type Output = Mat4<T>;
+
+    #[inline]
+    fn mul(self, s: T) -> Mat4<T> {
+        let mut out = self;
+        mul::smul(&mut out.0, &self.0, s);
+        out
+    }
+}
+impl<T: Copy + Num> Div<T> for Mat4<T> {
+    type Output = Mat4<T>;
+
+    #[inline]
+    fn div(self, s: T) -> Mat4<T> {
+        let mut out = self;
+        sdiv::sdiv(&mut out.0, &self.0, s);
+        out
+    }
+}
+impl<T: Kernel> MulAssign<Mat4<T>> for Mat4<T> {
+    #[inline]
+    fn mul_assign(&mut self, b: Mat4<T>) {
+        let a = *self;
+        simd::mul_simd(&mut self.0, &a.0, &b.0);
+    }
+}
+impl<T: Copy + Num> MulAssign<T> for Mat4<T> {
+    #[inline]
+    fn mul_assign(&mut self, s: T) {
+        let a = *self;
+        mul::smul(&mut self.0, &a.0, s);
+    }
+}
+
+impl<T: Copy + Num + ApproxEq> PartialEq for Mat4<T> {
+    #[inline]
+    fn eq(&self, other: &Mat4<T>) -> bool { misc::eq(&self.0, &other.0) }
+}
+
+#[test]
+fn test_mat4_mul() {
+    let a = Mat4::identity().translate(&[1f32, 2f32, 3f32]);
+    let b = Mat4::identity().scale(&[2f32, 2f32, 2f32]);
+    let mut out = [0f32; 16];
+    mul::mul(&mut out, &a.0, &b.0);
+    assert_eq!((a * b).0, out);
+    assert_eq!(&a * &b, Mat4(out));
+    let mut inv = [0f32; 16];
+    misc::inverse(&mut inv, &a.0);
+    assert_eq!(a.inverse().0, inv);
+    let mut t = [0f32; 16];
+    misc::transpose(&mut t, &a.0);
+    assert_eq!(a.transpose().0, t);
+
+    let mut c = a;
+    c *= b;
+    assert_eq!(c, Mat4(out));
+    assert_eq!((a * 2f32) / 2f32, a);
+}
+#[test]
+fn test_mat4_index() {
+    let m = Mat4::identity().translate(&[1f32, 2f32, 3f32]);
+    assert_eq!(m[(0, 3)], 1f32);
+    assert_eq!(m[(1, 3)], 2f32);
+    assert_eq!(m[(2, 3)], 3f32);
+    assert_eq!(m[(3, 3)], 1f32);
+    assert_eq!(m[(3, 0)], 0f32);
+    let a: [f32; 16] = m.into();
+    assert_eq!(Mat4::from(a), m);
+}
@@ -1,10 +1,54 @@
 use approx_eq::ApproxEq;
 use num::Num;
 use set::identity;
+#[cfg(test)]
+use test_util::{assert_close, Rng};
 
 
 #[inline]
-pub fn inverse<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+pub(crate) fn abs<T: Copy + Num>(x: T) -> T {
+    if x < T::zero() {T::zero() - x} else {x}
+}
+
+#[inline]
+pub fn epsilon<T: Copy + Num>() -> T {
+    let e = T::from_f64(f64::EPSILON);
+    if e > T::zero() && T::one() + e != T::one() {
+        return e;
+    }
+    let e = T::from_f32(f32::EPSILON);
+    if e > T::zero() && T::one() + e != T::one() {
+        return e;
+    }
+
+    let two = T::from_isize(2isize);
+    let mut e = T::one();
+    while T::one() + e / two != T::one() {
+        e = e / two;
+    }
+    e
+}
+#[test]
+fn test_epsilon() {
+    assert_eq!(epsilon::<f32>(), f32::EPSILON);
+    assert_eq!(epsilon::<f64>(), f64::EPSILON);
+    assert_eq!(epsilon::<i32>(), 1);
+}
+
+#[inline]
+pub fn max_abs<'b, T: Copy + Num>(a: &'b [T; 16]) -> T {
+    let mut m = T::zero();
+    for &v in a.iter() {
+        let v = abs(v);
+        if v > m {
+            m = v;
+        }
+    }
+    m
+}
+
+#[inline]
+fn inverse_eps<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], eps: T) -> bool {
     let a00 = a[0];
     let a01 = a[1];
     let a02 = a[2];
@@ -37,7 +81,22 @@ pub fn inverse<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &
 
     let d = b00 * b11 - b01 * b10 + b02 * b09 + b03 * b08 - b04 * b07 + b05 * b06;
 
-    if d != T::zero() {
+    // |det| over the product of the column lengths is the volume spanned by
+    // the unit columns: 1 when they are orthogonal, 0 when they are
+    // dependent, and unchanged by scaling any one column. A translation has
+    // no bearing on whether an affine matrix inverts, so there the last
+    // column only counts through a33.
+    let singular = d == T::zero() || (eps > T::zero() && {
+        let length = |x: T, y: T, z: T, w: T| (x * x + y * y + z * z + w * w).sqrt();
+        let w = if a03 == T::zero() && a13 == T::zero() && a23 == T::zero() {
+            abs(a33)
+        } else {
+            length(a30, a31, a32, a33)
+        };
+        abs(d) < eps * length(a00, a01, a02, a03) * length(a10, a11, a12, a13) * length(a20, a21, a22, a23) * w
+    });
+
+    if !singular {
         let inv_d = T::one() / d;
 
         out[0] = (a11 * b11 - a12 * b10 + a13 * b09) * inv_d;
@@ -56,6 +115,120 @@ pub fn inverse<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &
         out[13] = (a00 * b09 - a01 * b07 + a02 * b06) * inv_d;
         out[14] = (a31 * b01 - a30 * b03 - a32 * b00) * inv_d;
         out[15] = (a20 * b03 - a21 * b01 + a22 * b00) * inv_d;
+        true
+    } else {
+        false
+    }
+}
+
+/// Inverts `a` into `out`, or returns `None` and leaves `out` untouched when
+/// the columns of `a` are nearly dependent: `|det(a)|` is below `eps` times
+/// the product of the column lengths. For affine `a` the translation column
+/// is left out and `|a_33|` used in its place, so neither the units of each
+/// axis nor the distance from the origin make a matrix singular.
+#[inline]
+pub fn try_inverse_eps<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], eps: T) -> Option<&'a mut [T; 16]> {
+    if inverse_eps(out, a, eps) {
+        Some(out)
+    } else {
+        None
+    }
+}
+#[test]
+fn test_try_inverse_eps() {
+    // z within a thousandth of a radian of x
+    let sheared = [
+        1f32, 0f32, 0f32, 0f32,
+        0f32, 1f32, 0f32, 0f32,
+        1f32, 0f32, 1e-3f32, 0f32,
+        0f32, 0f32, 0f32, 1f32
+    ];
+    let mut v = [0f32; 16];
+    assert!(try_inverse_eps(&mut v, &sheared, 1e-2f32).is_none());
+    assert_eq!(v, [0f32; 16]);
+    assert!(try_inverse_eps(&mut v, &sheared, 1e-4f32).is_some());
+    assert!(v[10] > 999.9f32 && v[10] < 1000.1f32);
+
+    // a tiny scale is not nearly singular, only small
+    assert!(try_inverse_eps(&mut v, &[
+        1f32, 0f32, 0f32, 0f32,
+        0f32, 1f32, 0f32, 0f32,
+        0f32, 0f32, 1e-3f32, 0f32,
+        0f32, 0f32, 0f32, 1f32
+    ], 1e-2f32).is_some());
+}
+
+/// Same as `try_inverse_eps` with a tolerance of `4 * epsilon::<T>()`. Integer
+/// types have no rounding to allow for, so for them only a zero determinant
+/// is singular.
+#[inline]
+pub fn try_inverse<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> Option<&'a mut [T; 16]> {
+    let e = epsilon::<T>();
+    let eps = if e < T::one() {T::from_isize(4isize) * e} else {T::zero()};
+    try_inverse_eps(out, a, eps)
+}
+#[test]
+fn test_try_inverse() {
+    let mut v = [0f32; 16];
+    assert!(try_inverse(&mut v, &[
+        1f32, 2f32, 3f32, 4f32,
+        2f32, 4f32, 6f32, 8f32,
+        0f32, 0f32, 1f32, 0f32,
+        0f32, 0f32, 0f32, 1f32
+    ]).is_none());
+    assert!(try_inverse(&mut v, &[
+        1e-3f32, 0f32, 0f32, 0f32,
+        0f32, 1e-3f32, 0f32, 0f32,
+        0f32, 0f32, 1e-3f32, 0f32,
+        0f32, 0f32, 0f32, 1e-3f32
+    ]).is_some());
+    assert!(eq(&v, &[
+        1e3f32, 0f32, 0f32, 0f32,
+        0f32, 1e3f32, 0f32, 0f32,
+        0f32, 0f32, 1e3f32, 0f32,
+        0f32, 0f32, 0f32, 1e3f32
+    ]));
+
+    // far from the origin is not nearly singular
+    let mut t = ::create::new_identity::<f32>();
+    t[12] = 100f32;
+    t[13] = -100f32;
+    t[14] = 100f32;
+    assert!(try_inverse(&mut v, &t).is_some());
+    assert_eq!(&v[12..], &[-100f32, 100f32, -100f32, 1f32]);
+    let mut t = ::create::new_identity::<f64>();
+    t[12] = 1e5f64;
+    t[13] = 1e5f64;
+    t[14] = 1e5f64;
+    let mut w = [0f64; 16];
+    assert!(try_inverse(&mut w, &t).is_some());
+    assert_eq!(&w[12..], &[-1e5f64, -1e5f64, -1e5f64, 1f64]);
+
+    // a typical view-projection, with the camera 100 units out
+    let mut projection = [0f32; 16];
+    let mut view = [0f32; 16];
+    let mut view_projection = [0f32; 16];
+    let mut product = [0f32; 16];
+    ::transform::perspective(&mut projection, 1f32, 1.5f32, 0.1f32, 1000f32);
+    ::transform::look_at(&mut view, &[0f32, 0f32, 100f32], &[0f32, 0f32, 0f32], &[0f32, 1f32, 0f32]);
+    ::mul::mul(&mut view_projection, &projection, &view);
+    assert!(try_inverse(&mut v, &view_projection).is_some());
+    ::mul::mul(&mut product, &view_projection, &v);
+    for (i, x) in product.iter().enumerate() {
+        assert!((x - if i % 5 == 0 {1f32} else {0f32}).abs() < 1e-4f32);
+    }
+
+    let mut n = [0i32; 16];
+    assert!(try_inverse(&mut n, &::create::new_identity::<i32>()).is_some());
+    assert_eq!(n, ::create::new_identity::<i32>());
+    assert!(try_inverse(&mut n, &[1, 2, 0, 0, 2, 4, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]).is_none());
+}
+
+/// Inverts `a` into `out`, writing the identity when the determinant is
+/// exactly zero. Use `try_inverse` to detect singular matrices.
+#[inline]
+pub fn inverse<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    if inverse_eps(out, a, T::zero()) {
         out
     } else {
         identity(out)
@@ -66,6 +239,251 @@ fn test_inverse() {
     let mut v = [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1];
     inverse(&mut v, &[1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]);
     assert!(v == [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]);
+
+    let mut v = [2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 2, 0, 0, 0, 0, 2];
+    inverse(&mut v, &[1, 2, 3, 4, 2, 4, 6, 8, 0, 0, 1, 0, 0, 0, 0, 1]);
+    assert!(v == [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 1]);
+}
+
+#[inline]
+pub fn is_affine<'b, T: Copy + Num>(a: &'b [T; 16]) -> bool {
+    a[3] == T::zero() && a[7] == T::zero() && a[11] == T::zero() && a[15] == T::one()
+}
+
+/// Whether the columns of the upper 3x3 of `a` are unit length and mutually
+/// orthogonal to within `eps`.
+#[inline]
+pub fn is_orthonormal<'b, T: Copy + Num>(a: &'b [T; 16], eps: T) -> bool {
+    let xx = a[0] * a[0] + a[1] * a[1] + a[2] * a[2];
+    let yy = a[4] * a[4] + a[5] * a[5] + a[6] * a[6];
+    let zz = a[8] * a[8] + a[9] * a[9] + a[10] * a[10];
+    let xy = a[0] * a[4] + a[1] * a[5] + a[2] * a[6];
+    let xz = a[0] * a[8] + a[1] * a[9] + a[2] * a[10];
+    let yz = a[4] * a[8] + a[5] * a[9] + a[6] * a[10];
+
+    abs(xx - T::one()) <= eps &&
+    abs(yy - T::one()) <= eps &&
+    abs(zz - T::one()) <= eps &&
+    abs(xy) <= eps &&
+    abs(xz) <= eps &&
+    abs(yz) <= eps
+}
+
+#[inline]
+pub fn is_rigid<'b, T: Copy + Num>(a: &'b [T; 16], eps: T) -> bool {
+    is_affine(a) && is_orthonormal(a, eps)
+}
+#[test]
+fn test_is_rigid() {
+    let eps = 16f32 * epsilon::<f32>();
+    let mut m = [0f32; 16];
+    ::transform::compose(&mut m, &[1f32, 2f32, 3f32], &[1f32, 1f32, 1f32], &[0f32, 0.6f32, 0f32, 0.8f32]);
+    assert!(is_affine(&m));
+    assert!(is_rigid(&m, eps));
+    ::transform::compose(&mut m, &[1f32, 2f32, 3f32], &[1f32, 2f32, 1f32], &[0f32, 0.6f32, 0f32, 0.8f32]);
+    assert!(is_affine(&m));
+    assert!(!is_rigid(&m, eps));
+    assert!(!is_orthonormal(&m, eps));
+    m[3] = 1f32;
+    assert!(!is_affine(&m));
+    ::transform::compose(&mut m, &[1f32, 2f32, 3f32], &[1f32, 1f32, 1f32], &[0f32, 0.6f32, 0f32, 0.8f32]);
+    m[3] = 1f32;
+    assert!(!is_rigid(&m, eps));
+    assert!(is_orthonormal(&m, eps));
+}
+
+/// Inverts an affine matrix (bottom row `0, 0, 0, 1`) using a 3x3 inverse of
+/// the linear part. Writes the identity if the linear part is singular.
+#[inline]
+pub fn inverse_affine<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    let a00 = a[0];
+    let a01 = a[1];
+    let a02 = a[2];
+    let a10 = a[4];
+    let a11 = a[5];
+    let a12 = a[6];
+    let a20 = a[8];
+    let a21 = a[9];
+    let a22 = a[10];
+    let tx = a[12];
+    let ty = a[13];
+    let tz = a[14];
+
+    let b01 = a22 * a11 - a12 * a21;
+    let b11 = a12 * a20 - a22 * a10;
+    let b21 = a21 * a10 - a11 * a20;
+
+    let d = a00 * b01 + a01 * b11 + a02 * b21;
+
+    if d != T::zero() {
+        let inv_d = T::one() / d;
+
+        out[0] = b01 * inv_d;
+        out[1] = (a02 * a21 - a22 * a01) * inv_d;
+        out[2] = (a12 * a01 - a02 * a11) * inv_d;
+        out[3] = T::zero();
+        out[4] = b11 * inv_d;
+        out[5] = (a22 * a00 - a02 * a20) * inv_d;
+        out[6] = (a02 * a10 - a12 * a00) * inv_d;
+        out[7] = T::zero();
+        out[8] = b21 * inv_d;
+        out[9] = (a01 * a20 - a21 * a00) * inv_d;
+        out[10] = (a11 * a00 - a01 * a10) * inv_d;
+        out[11] = T::zero();
+        out[12] = T::zero() - (out[0] * tx + out[4] * ty + out[8] * tz);
+        out[13] = T::zero() - (out[1] * tx + out[5] * ty + out[9] * tz);
+        out[14] = T::zero() - (out[2] * tx + out[6] * ty + out[10] * tz);
+        out[15] = T::one();
+        out
+    } else {
+        identity(out)
+    }
+}
+
+/// Inverts a rigid matrix (orthonormal rotation plus translation) by
+/// transposing the rotation. The result is only correct if `a` is rigid.
+#[inline]
+pub fn inverse_rigid<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    let tx = a[12];
+    let ty = a[13];
+    let tz = a[14];
+
+    out[0] = a[0];
+    out[1] = a[4];
+    out[2] = a[8];
+    out[3] = T::zero();
+    out[4] = a[1];
+    out[5] = a[5];
+    out[6] = a[9];
+    out[7] = T::zero();
+    out[8] = a[2];
+    out[9] = a[6];
+    out[10] = a[10];
+    out[11] = T::zero();
+    out[12] = T::zero() - (a[0] * tx + a[1] * ty + a[2] * tz);
+    out[13] = T::zero() - (a[4] * tx + a[5] * ty + a[6] * tz);
+    out[14] = T::zero() - (a[8] * tx + a[9] * ty + a[10] * tz);
+    out[15] = T::one();
+    out
+}
+
+/// Inverts `a` using the cheapest path its structure allows: `inverse_rigid`,
+/// `inverse_affine` or the general `inverse`.
+#[inline]
+pub fn inverse_auto<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    if is_affine(a) {
+        if is_rigid(a, T::from_isize(16isize) * epsilon::<T>()) {
+            inverse_rigid(out, a)
+        } else {
+            inverse_affine(out, a)
+        }
+    } else {
+        inverse(out, a)
+    }
+}
+
+#[test]
+fn test_inverse_affine() {
+    let mut rng = Rng(1);
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    for _ in 0..100 {
+        let m = rng.trs(false);
+        inverse(&mut a, &m);
+        inverse_affine(&mut b, &m);
+        assert_close(&a, &b, 1e-9f64);
+        inverse_auto(&mut b, &m);
+        assert_close(&a, &b, 1e-9f64);
+    }
+
+    // the sheared y column has to be undone through the inverse of the
+    // whole 3x3, not just by dividing out each column's length
+    inverse_affine(&mut b, &[
+        2f64, 0f64, 0f64, 0f64,
+        1f64, 4f64, 0f64, 0f64,
+        0f64, 0f64, 8f64, 0f64,
+        1f64, 2f64, 3f64, 1f64
+    ]);
+    assert_close(&b, &[
+        0.5f64, 0f64, 0f64, 0f64,
+        -0.125f64, 0.25f64, 0f64, 0f64,
+        0f64, 0f64, 0.125f64, 0f64,
+        -0.25f64, -0.5f64, -0.375f64, 1f64
+    ], 1e-15f64);
+}
+#[test]
+fn test_inverse_rigid() {
+    let mut rng = Rng(7);
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    for _ in 0..100 {
+        let m = rng.trs(true);
+        assert!(is_rigid(&m, 16f64 * epsilon::<f64>()));
+        inverse(&mut a, &m);
+        inverse_rigid(&mut b, &m);
+        assert_close(&a, &b, 1e-9f64);
+        inverse_auto(&mut b, &m);
+        assert_close(&a, &b, 1e-9f64);
+    }
+
+    // x -> y -> z -> x is not symmetric, so returning R instead of its
+    // transpose, or rotating the translation the wrong way, shows up exactly
+    inverse_rigid(&mut b, &[
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        1f64, 2f64, 3f64, 1f64
+    ]);
+    assert_eq!(b, [
+        0f64, 0f64, 1f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        -2f64, -3f64, -1f64, 1f64
+    ]);
+}
+
+#[inline]
+pub fn norm_inf<'b, T: Copy + Num>(a: &'b [T; 16]) -> T {
+    let mut m = T::zero();
+    for i in 0..4 {
+        let row = abs(a[i]) + abs(a[i + 4]) + abs(a[i + 8]) + abs(a[i + 12]);
+        if row > m {
+            m = row;
+        }
+    }
+    m
+}
+
+/// Infinity-norm condition number `|a| * |a^-1|`, or `None` if `a` is
+/// singular. The inverse loses roughly `log10(condition)` decimal digits, so
+/// values approaching `1 / epsilon::<T>()` mean the inverse is meaningless.
+#[inline]
+pub fn condition<'b, T: Copy + Num>(a: &'b [T; 16]) -> Option<T> {
+    let mut inv = [T::zero(); 16];
+
+    if inverse_eps(&mut inv, a, T::zero()) {
+        Some(norm_inf(a) * norm_inf(&inv))
+    } else {
+        None
+    }
+}
+#[test]
+fn test_condition() {
+    let c = condition(&[
+        3f32, 0f32, 0f32, 0f32,
+        0f32, 3f32, 0f32, 0f32,
+        0f32, 0f32, 3f32, 0f32,
+        0f32, 0f32, 0f32, 3f32
+    ]).unwrap();
+    assert!((c - 1f32).abs() <= 16f32 * epsilon::<f32>());
+    let c = condition(&[
+        1f32, 0f32, 0f32, 0f32,
+        0f32, 1f32, 0f32, 0f32,
+        0f32, 0f32, 1e-6f32, 0f32,
+        0f32, 0f32, 0f32, 1f32
+    ]).unwrap();
+    assert!((c - 1e6f32).abs() <= 1e6f32 * 16f32 * epsilon::<f32>());
+    assert_eq!(condition(&[0f32; 16]), None);
 }
 
 #[inline]
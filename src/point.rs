@@ -0,0 +1,190 @@
+use num::Num;
+use vec3;
+
+#[cfg(test)]
+use test_util::assert_close;
+
+
+#[inline]
+pub fn transform_vec4<'a, 'b, T: Copy + Num>(out: &'a mut [T; 4], m: &'b [T; 16], v: &'b [T; 4]) -> &'a mut [T; 4] {
+    let x = v[0];
+    let y = v[1];
+    let z = v[2];
+    let w = v[3];
+
+    out[0] = m[0] * x + m[4] * y + m[8] * z + m[12] * w;
+    out[1] = m[1] * x + m[5] * y + m[9] * z + m[13] * w;
+    out[2] = m[2] * x + m[6] * y + m[10] * z + m[14] * w;
+    out[3] = m[3] * x + m[7] * y + m[11] * z + m[15] * w;
+    out
+}
+#[test]
+fn test_transform_vec4() {
+    let mut v = [0f32; 4];
+    transform_vec4(&mut v, &[
+        1f32, 0f32, 0f32, 0f32,
+        0f32, 1f32, 0f32, 0f32,
+        0f32, 0f32, 1f32, 0f32,
+        1f32, 2f32, 3f32, 1f32
+    ], &[1f32, 1f32, 1f32, 2f32]);
+    assert_eq!(v, [3f32, 5f32, 7f32, 2f32]);
+}
+
+/// Transforms the point `p` (with `w = 1`) and divides by the resulting `w`
+/// unless it is zero.
+#[inline]
+pub fn transform_point3<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], m: &'b [T; 16], p: &'b [T; 3]) -> &'a mut [T; 3] {
+    let x = p[0];
+    let y = p[1];
+    let z = p[2];
+    let w = m[3] * x + m[7] * y + m[11] * z + m[15];
+    let inv_w = if w != T::zero() {T::one() / w} else {T::one()};
+
+    out[0] = (m[0] * x + m[4] * y + m[8] * z + m[12]) * inv_w;
+    out[1] = (m[1] * x + m[5] * y + m[9] * z + m[13]) * inv_w;
+    out[2] = (m[2] * x + m[6] * y + m[10] * z + m[14]) * inv_w;
+    out
+}
+#[test]
+fn test_transform_point3() {
+    let mut v = [0f32; 3];
+    transform_point3(&mut v, &[
+        1f32, 0f32, 0f32, 0f32,
+        0f32, 1f32, 0f32, 0f32,
+        0f32, 0f32, 1f32, 0f32,
+        1f32, 2f32, 3f32, 1f32
+    ], &[1f32, 1f32, 1f32]);
+    assert_eq!(v, [2f32, 3f32, 4f32]);
+    transform_point3(&mut v, &[
+        2f32, 0f32, 0f32, 0f32,
+        0f32, 2f32, 0f32, 0f32,
+        0f32, 0f32, 2f32, 0f32,
+        0f32, 0f32, 0f32, 2f32
+    ], &[1f32, 2f32, 3f32]);
+    assert_eq!(v, [1f32, 2f32, 3f32]);
+}
+
+/// Transforms the direction `v`, ignoring translation and perspective.
+#[inline]
+pub fn transform_vector3<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], m: &'b [T; 16], v: &'b [T; 3]) -> &'a mut [T; 3] {
+    let x = v[0];
+    let y = v[1];
+    let z = v[2];
+
+    out[0] = m[0] * x + m[4] * y + m[8] * z;
+    out[1] = m[1] * x + m[5] * y + m[9] * z;
+    out[2] = m[2] * x + m[6] * y + m[10] * z;
+    out
+}
+#[test]
+fn test_transform_vector3() {
+    let mut v = [0f32; 3];
+    transform_vector3(&mut v, &[
+        0f32, 1f32, 0f32, 0f32,
+        -1f32, 0f32, 0f32, 0f32,
+        0f32, 0f32, 1f32, 0f32,
+        1f32, 2f32, 3f32, 1f32
+    ], &[1f32, 2f32, 3f32]);
+    assert_eq!(v, [-2f32, 1f32, 3f32]);
+}
+
+/// Transforms the surface normal `n` by the inverse transpose of the upper
+/// 3x3 of `m` and normalizes the result, so non-uniform scale and mirroring
+/// keep normals perpendicular to the transformed surface.
+#[inline]
+pub fn transform_normal<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], m: &'b [T; 16], n: &'b [T; 3]) -> &'a mut [T; 3] {
+    let x0 = m[0];
+    let x1 = m[1];
+    let x2 = m[2];
+    let y0 = m[4];
+    let y1 = m[5];
+    let y2 = m[6];
+    let z0 = m[8];
+    let z1 = m[9];
+    let z2 = m[10];
+    let nx = n[0];
+    let ny = n[1];
+    let nz = n[2];
+
+    // columns of the cofactor matrix, which is the inverse transpose times det
+    let c00 = y1 * z2 - y2 * z1;
+    let c01 = y2 * z0 - y0 * z2;
+    let c02 = y0 * z1 - y1 * z0;
+    let c10 = z1 * x2 - z2 * x1;
+    let c11 = z2 * x0 - z0 * x2;
+    let c12 = z0 * x1 - z1 * x0;
+    let c20 = x1 * y2 - x2 * y1;
+    let c21 = x2 * y0 - x0 * y2;
+    let c22 = x0 * y1 - x1 * y0;
+    let d = x0 * c00 + x1 * c01 + x2 * c02;
+
+    let mut rx = c00 * nx + c10 * ny + c20 * nz;
+    let mut ry = c01 * nx + c11 * ny + c21 * nz;
+    let mut rz = c02 * nx + c12 * ny + c22 * nz;
+
+    let len = vec3::length_values(rx, ry, rz);
+    if len != T::zero() {
+        let inv_len = if d < T::zero() {T::zero() - T::one() / len} else {T::one() / len};
+        rx = rx * inv_len;
+        ry = ry * inv_len;
+        rz = rz * inv_len;
+    }
+
+    out[0] = rx;
+    out[1] = ry;
+    out[2] = rz;
+    out
+}
+#[test]
+fn test_transform_normal() {
+    let mut n = [0f64; 3];
+    let h = 0.5f64.sqrt();
+    // a 45 degree plane squashed along x tilts its normal towards x
+    transform_normal(&mut n, &[
+        0.5f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        5f64, 6f64, 7f64, 1f64
+    ], &[h, h, 0f64]);
+    let l = 5f64.sqrt();
+    assert_close(&n, &[2f64 / l, 1f64 / l, 0f64], 1e-12f64);
+
+    transform_normal(&mut n, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], &[1f64, 0f64, 0f64]);
+    assert_close(&n, &[-1f64, 0f64, 0f64], 1e-12f64);
+}
+
+/// Projects the point `p` through `m`, returning `None` when it lands on or
+/// behind the `w = 0` plane, i.e. behind the eye of a perspective projection.
+#[inline]
+pub fn project_point3<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], m: &'b [T; 16], p: &'b [T; 3]) -> Option<&'a mut [T; 3]> {
+    let x = p[0];
+    let y = p[1];
+    let z = p[2];
+    let w = m[3] * x + m[7] * y + m[11] * z + m[15];
+
+    if w > T::zero() {
+        let inv_w = T::one() / w;
+        out[0] = (m[0] * x + m[4] * y + m[8] * z + m[12]) * inv_w;
+        out[1] = (m[1] * x + m[5] * y + m[9] * z + m[13]) * inv_w;
+        out[2] = (m[2] * x + m[6] * y + m[10] * z + m[14]) * inv_w;
+        Some(out)
+    } else {
+        None
+    }
+}
+#[test]
+fn test_project_point3() {
+    let mut m = [0f64; 16];
+    let mut v = [0f64; 3];
+    ::transform::frustum(&mut m, 1f64, 1f64, -1f64, -1f64, 1f64, 10f64);
+    assert!(project_point3(&mut v, &m, &[0f64, 0f64, -1f64]).is_some());
+    assert_close(&v, &[0f64, 0f64, -1f64], 1e-12f64);
+    assert!(project_point3(&mut v, &m, &[10f64, 10f64, -10f64]).is_some());
+    assert_close(&v, &[1f64, 1f64, 1f64], 1e-12f64);
+    assert!(project_point3(&mut v, &m, &[0f64, 0f64, 1f64]).is_none());
+}
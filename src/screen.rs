@@ -0,0 +1,224 @@
+use num::Num;
+use vec3;
+
+use clip::ClipSpace;
+
+use misc::try_inverse;
+use mul::mul;
+use point::project_point3;
+#[cfg(test)]
+use test_util::assert_close;
+#[cfg(test)]
+use transform::{look_at, perspective, perspective_clip};
+
+
+/// Maps the world-space point `p` to window coordinates like `gluProject`.
+/// `viewport` is `[x, y, width, height]` and the window depth is in `[0, 1]`.
+/// Returns `None` if the point is behind the eye.
+#[inline]
+pub fn project<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], p: &'b [T; 3], view_proj: &'b [T; 16], viewport: &'b [T; 4]) -> Option<&'a mut [T; 3]> {
+    project_clip(out, p, view_proj, viewport, &ClipSpace::opengl())
+}
+
+/// Same as `project` for projections built for the clip space `clip`.
+#[inline]
+pub fn project_clip<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], p: &'b [T; 3], view_proj: &'b [T; 16], viewport: &'b [T; 4], clip: &'b ClipSpace) -> Option<&'a mut [T; 3]> {
+    let mut ndc = [T::zero(); 3];
+
+    project_point3(&mut ndc, view_proj, p)?;
+    let half = T::from_f32(0.5f32);
+
+    out[0] = viewport[0] + (ndc[0] + T::one()) * half * viewport[2];
+    out[1] = viewport[1] + (ndc[1] + T::one()) * half * viewport[3];
+    out[2] = clip.ndc_to_window(ndc[2]);
+    Some(out)
+}
+#[test]
+fn test_project() {
+    let mut m = [0f64; 16];
+    let mut v = [0f64; 3];
+    ::transform::frustum(&mut m, 1f64, 1f64, -1f64, -1f64, 1f64, 10f64);
+    let viewport = [10f64, 20f64, 800f64, 600f64];
+    assert!(project(&mut v, &[0f64, 0f64, -1f64], &m, &viewport).is_some());
+    assert_close(&v, &[410f64, 320f64, 0f64], 1e-12f64);
+    assert!(project(&mut v, &[-10f64, 10f64, -10f64], &m, &viewport).is_some());
+    assert_close(&v, &[10f64, 620f64, 1f64], 1e-12f64);
+    assert!(project(&mut v, &[0f64, 0f64, 1f64], &m, &viewport).is_none());
+}
+
+/// Maps window coordinates back to world space like `gluUnProject`, given the
+/// inverse of the view-projection. Returns `None` for points at infinity.
+#[inline]
+pub fn unproject<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], win: &'b [T; 3], inv_view_proj: &'b [T; 16], viewport: &'b [T; 4]) -> Option<&'a mut [T; 3]> {
+    unproject_clip(out, win, inv_view_proj, viewport, &ClipSpace::opengl())
+}
+
+/// Same as `unproject` for projections built for the clip space `clip`.
+#[inline]
+pub fn unproject_clip<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], win: &'b [T; 3], inv_view_proj: &'b [T; 16], viewport: &'b [T; 4], clip: &'b ClipSpace) -> Option<&'a mut [T; 3]> {
+    let two = T::from_isize(2isize);
+    let x = (win[0] - viewport[0]) * two / viewport[2] - T::one();
+    let y = (win[1] - viewport[1]) * two / viewport[3] - T::one();
+    let z = clip.window_to_ndc(win[2]);
+    let m = inv_view_proj;
+    let w = m[3] * x + m[7] * y + m[11] * z + m[15];
+
+    if w == T::zero() {
+        return None;
+    }
+    let inv_w = T::one() / w;
+
+    out[0] = (m[0] * x + m[4] * y + m[8] * z + m[12]) * inv_w;
+    out[1] = (m[1] * x + m[5] * y + m[9] * z + m[13]) * inv_w;
+    out[2] = (m[2] * x + m[6] * y + m[10] * z + m[14]) * inv_w;
+    Some(out)
+}
+#[test]
+fn test_unproject() {
+    let mut proj = [0f64; 16];
+    let mut view = [0f64; 16];
+    let mut m = [0f64; 16];
+    let mut inv = [0f64; 16];
+    perspective(&mut proj, 1f64, 4f64 / 3f64, 0.5f64, 50f64);
+    look_at(&mut view, &[3f64, 4f64, 5f64], &[0f64, 1f64, 0f64], &[0f64, 1f64, 0f64]);
+    mul(&mut m, &proj, &view);
+    ::misc::inverse(&mut inv, &m);
+
+    let viewport = [0f64, 0f64, 800f64, 600f64];
+    let p = [1f64, 2f64, -1f64];
+    let mut win = [0f64; 3];
+    let mut v = [0f64; 3];
+    assert!(project(&mut win, &p, &m, &viewport).is_some());
+    assert!(unproject(&mut v, &win, &inv, &viewport).is_some());
+    assert_close(&v, &p, 1e-9f64);
+}
+
+/// Builds a world-space picking ray through the window position `(x, y)`,
+/// starting on the near plane. Returns `false` if `proj * view` is singular.
+#[inline]
+pub fn screen_ray<'b, T: Copy + Num>(
+    origin: &mut [T; 3],
+    direction: &mut [T; 3],
+    x: T,
+    y: T,
+    view: &'b [T; 16],
+    proj: &'b [T; 16],
+    viewport: &'b [T; 4]
+) -> bool {
+    screen_ray_clip(origin, direction, x, y, view, proj, viewport, &ClipSpace::opengl())
+}
+
+/// Same as `screen_ray` for projections built for the clip space `clip`.
+#[inline]
+pub fn screen_ray_clip<'b, T: Copy + Num>(
+    origin: &mut [T; 3],
+    direction: &mut [T; 3],
+    x: T,
+    y: T,
+    view: &'b [T; 16],
+    proj: &'b [T; 16],
+    viewport: &'b [T; 4],
+    clip: &'b ClipSpace
+) -> bool {
+    let mut m = [T::zero(); 16];
+    let mut inv = [T::zero(); 16];
+    let mut near = [T::zero(); 3];
+    let mut far = [T::zero(); 3];
+    mul(&mut m, proj, view);
+
+    let (zn, zf) = clip.near_far::<T>();
+    let near_z = clip.ndc_to_window(zn);
+    let mut far_z = clip.ndc_to_window(zf);
+    // the far plane of an infinite projection unprojects to a point at
+    // infinity, any depth in between lies on the same ray
+    if clip.infinite {
+        far_z = (near_z + far_z) * T::from_f32(0.5f32);
+    }
+
+    if try_inverse(&mut inv, &m).is_none() ||
+       unproject_clip(&mut near, &[x, y, near_z], &inv, viewport, clip).is_none() ||
+       unproject_clip(&mut far, &[x, y, far_z], &inv, viewport, clip).is_none() {
+        return false;
+    }
+
+    let dx = far[0] - near[0];
+    let dy = far[1] - near[1];
+    let dz = far[2] - near[2];
+    let len = vec3::length_values(dx, dy, dz);
+    let inv_len = if len != T::zero() {T::one() / len} else {T::zero()};
+
+    origin[0] = near[0];
+    origin[1] = near[1];
+    origin[2] = near[2];
+    direction[0] = dx * inv_len;
+    direction[1] = dy * inv_len;
+    direction[2] = dz * inv_len;
+    true
+}
+#[test]
+fn test_screen_ray() {
+    let mut proj = [0f64; 16];
+    let mut view = [0f64; 16];
+    perspective(&mut proj, 0.5f64 * ::core::f64::consts::PI, 1f64, 1f64, 100f64);
+    look_at(&mut view, &[0f64, 0f64, 10f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+
+    let viewport = [0f64, 0f64, 100f64, 100f64];
+    let mut origin = [0f64; 3];
+    let mut direction = [0f64; 3];
+    assert!(screen_ray(&mut origin, &mut direction, 50f64, 50f64, &view, &proj, &viewport));
+    assert_close(&origin, &[0f64, 0f64, 9f64], 1e-9f64);
+    assert_close(&direction, &[0f64, 0f64, -1f64], 1e-9f64);
+
+    // the right edge of a 90 degree frustum is at 45 degrees
+    let h = 0.5f64.sqrt();
+    assert!(screen_ray(&mut origin, &mut direction, 100f64, 50f64, &view, &proj, &viewport));
+    assert_close(&origin, &[1f64, 0f64, 9f64], 1e-9f64);
+    assert_close(&direction, &[h, 0f64, -h], 1e-9f64);
+
+    // f32 with the camera well away from the origin
+    let mut proj = [0f32; 16];
+    let mut view = [0f32; 16];
+    perspective(&mut proj, 0.5f32 * ::core::f32::consts::PI, 1f32, 1f32, 1000f32);
+    look_at(&mut view, &[0f32, 0f32, 100f32], &[0f32, 0f32, 0f32], &[0f32, 1f32, 0f32]);
+    let mut origin = [0f32; 3];
+    let mut direction = [0f32; 3];
+    assert!(screen_ray(&mut origin, &mut direction, 50f32, 50f32, &view, &proj, &[0f32, 0f32, 100f32, 100f32]));
+    assert!(origin[0].abs() < 1e-4f32 && origin[1].abs() < 1e-4f32 && (origin[2] - 99f32).abs() < 1e-3f32);
+    assert!(direction[0].abs() < 1e-4f32 && direction[1].abs() < 1e-4f32 && (direction[2] + 1f32).abs() < 1e-4f32);
+}
+#[test]
+fn test_screen_ray_clip() {
+    let configs = [
+        ClipSpace::opengl(),
+        ClipSpace::opengl().reversed(),
+        ClipSpace::zero_to_one(),
+        ClipSpace::zero_to_one().reversed(),
+    ];
+    let mut proj = [0f64; 16];
+    let mut view = [0f64; 16];
+    let mut m = [0f64; 16];
+    let mut inv = [0f64; 16];
+    let mut win = [0f64; 3];
+    let mut v = [0f64; 3];
+    let viewport = [0f64, 0f64, 100f64, 100f64];
+    let h = 0.5f64.sqrt();
+    look_at(&mut view, &[0f64, 0f64, 10f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+
+    for clip in configs.iter() {
+        for &clip in [*clip, clip.infinite()].iter() {
+            let mut origin = [0f64; 3];
+            let mut direction = [0f64; 3];
+            perspective_clip(&mut proj, 0.5f64 * ::core::f64::consts::PI, 1f64, 1f64, 100f64, &clip);
+            assert!(screen_ray_clip(&mut origin, &mut direction, 100f64, 50f64, &view, &proj, &viewport, &clip));
+            assert_close(&origin, &[1f64, 0f64, 9f64], 1e-9f64);
+            assert_close(&direction, &[h, 0f64, -h], 1e-9f64);
+
+            mul(&mut m, &proj, &view);
+            ::misc::inverse(&mut inv, &m);
+            assert!(project_clip(&mut win, &[1f64, 2f64, -3f64], &m, &viewport, &clip).is_some());
+            assert!(win[2] >= 0f64 && win[2] <= 1f64);
+            assert!(unproject_clip(&mut v, &win, &inv, &viewport, &clip).is_some());
+            assert_close(&v, &[1f64, 2f64, -3f64], 1e-9f64);
+        }
+    }
+}
@@ -0,0 +1,473 @@
+use num::Num;
+
+use expm::{rodrigues, so3_coefficients, so3_inverse_coefficient};
+use misc::epsilon;
+use transform::{from_axis_angle, to_axis_angle};
+#[cfg(test)]
+use test_util::{assert_close, Rng};
+
+
+// Twists are `[v, w]`: linear velocity (or translation) first, then angular
+// velocity (or rotation vector), matching the block order of the 6x6
+// matrices. 3x3 matrices are column major `[T; 9]` as taken by `from_mat3`,
+// 6x6 matrices column major `[T; 36]`.
+
+#[inline]
+fn neg<T: Copy + Num>(x: T) -> T {
+    T::zero() - x
+}
+
+#[inline]
+fn mul3<T: Copy + Num>(m: &[T; 9], v: &[T; 3]) -> [T; 3] {
+    [
+        m[0] * v[0] + m[3] * v[1] + m[6] * v[2],
+        m[1] * v[0] + m[4] * v[1] + m[7] * v[2],
+        m[2] * v[0] + m[5] * v[1] + m[8] * v[2],
+    ]
+}
+
+// writes the 3x3 `m` into block (`row`, `col`) of the 6x6 `out`
+#[inline]
+fn set_block<T: Copy + Num>(out: &mut [T; 36], row: usize, col: usize, m: &[T; 9]) {
+    for j in 0..3 {
+        for i in 0..3 {
+            out[(col * 3 + j) * 6 + row * 3 + i] = m[j * 3 + i];
+        }
+    }
+}
+
+/// Skew-symmetric cross product matrix of `w`.
+#[inline]
+pub fn so3_hat<'a, 'b, T: Copy + Num>(out: &'a mut [T; 9], w: &'b [T; 3]) -> &'a mut [T; 9] {
+    rodrigues(out, w, T::zero(), T::one(), T::zero());
+    out
+}
+
+#[inline]
+pub fn so3_vee<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], m: &'b [T; 9]) -> &'a mut [T; 3] {
+    out[0] = m[5];
+    out[1] = m[6];
+    out[2] = m[1];
+    out
+}
+
+/// Rotation by the rotation vector `w`.
+#[inline]
+pub fn so3_exp<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], w: &'b [T; 3]) -> &'a mut [T; 16] {
+    let theta = (w[0] * w[0] + w[1] * w[1] + w[2] * w[2]).sqrt();
+    from_axis_angle(out, w, theta)
+}
+
+/// Rotation vector of the upper 3x3 of `m`, with an angle in `[0, pi]`.
+#[inline]
+pub fn so3_log<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], m: &'b [T; 16]) -> &'a mut [T; 3] {
+    let mut axis = [T::zero(); 3];
+    let theta = to_axis_angle(m, &mut axis);
+    out[0] = axis[0] * theta;
+    out[1] = axis[1] * theta;
+    out[2] = axis[2] * theta;
+    out
+}
+#[test]
+fn test_so3() {
+    let mut rng = Rng(127);
+    let mut m = [0f64; 16];
+    let mut h = [0f64; 9];
+    let mut w = [0f64; 3];
+    for _ in 0..20 {
+        let q = rng.quat();
+        ::transform::from_quat(&mut m, &q);
+        so3_log(&mut w, &m);
+        let mut n = [0f64; 16];
+        so3_exp(&mut n, &w);
+        assert_close(&n, &m, 1e-12f64);
+
+        so3_hat(&mut h, &w);
+        let mut v = [0f64; 3];
+        so3_vee(&mut v, &h);
+        assert_eq!(v, w);
+        let c = mul3(&h, &[1f64, 2f64, 3f64]);
+        assert_close(&c, &[w[1] * 3f64 - w[2] * 2f64, w[2] - w[0] * 3f64, w[0] * 2f64 - w[1]], 1e-12f64);
+    }
+
+    // half a turn, where the rotation is symmetric and the axis has to come
+    // from its diagonal rather than its skew part
+    let pi = ::core::f64::consts::PI;
+    so3_exp(&mut m, &[pi, 0f64, 0f64]);
+    assert_close(&m, &[
+        1f64, 0f64, 0f64, 0f64,
+        0f64, -1f64, 0f64, 0f64,
+        0f64, 0f64, -1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+    so3_log(&mut w, &m);
+    assert_close(&[w[0].abs(), w[1], w[2]], &[pi, 0f64, 0f64], 1e-12f64);
+}
+
+/// SO(3) left Jacobian: `exp(w + d) ~ exp(J d) * exp(w)` for small `d`.
+#[inline]
+pub fn so3_left_jacobian<'a, 'b, T: Copy + Num>(out: &'a mut [T; 9], w: &'b [T; 3]) -> &'a mut [T; 9] {
+    let (_, b, c) = so3_coefficients(w[0] * w[0] + w[1] * w[1] + w[2] * w[2]);
+    rodrigues(out, w, T::one(), b, c);
+    out
+}
+
+/// SO(3) right Jacobian: `exp(w + d) ~ exp(w) * exp(J d)` for small `d`.
+#[inline]
+pub fn so3_right_jacobian<'a, 'b, T: Copy + Num>(out: &'a mut [T; 9], w: &'b [T; 3]) -> &'a mut [T; 9] {
+    so3_left_jacobian(out, &[neg(w[0]), neg(w[1]), neg(w[2])])
+}
+
+#[inline]
+pub fn so3_left_jacobian_inverse<'a, 'b, T: Copy + Num>(out: &'a mut [T; 9], w: &'b [T; 3]) -> &'a mut [T; 9] {
+    let c = so3_inverse_coefficient(w[0] * w[0] + w[1] * w[1] + w[2] * w[2]);
+    rodrigues(out, w, T::one(), T::from_f32(-0.5f32), c);
+    out
+}
+#[test]
+fn test_so3_jacobians() {
+    let mut rng = Rng(131);
+    let h = 1e-6f64;
+    for _ in 0..10 {
+        let q = rng.quat();
+        let w = [q[0] * 2f64, q[1] * 2f64, q[2] * 2f64];
+        let mut jl = [0f64; 9];
+        let mut jr = [0f64; 9];
+        let mut inv = [0f64; 9];
+        so3_left_jacobian(&mut jl, &w);
+        so3_right_jacobian(&mut jr, &w);
+        so3_left_jacobian_inverse(&mut inv, &w);
+
+        let mut rw = [0f64; 16];
+        let mut rw_t = [0f64; 16];
+        so3_exp(&mut rw, &w);
+        ::misc::transpose(&mut rw_t, &rw);
+        for k in 0..3 {
+            let mut wd = w;
+            wd[k] += h;
+            let mut rd = [0f64; 16];
+            let mut tmp = [0f64; 16];
+            let mut d = [0f64; 3];
+            so3_exp(&mut rd, &wd);
+
+            ::mul::mul(&mut tmp, &rd, &rw_t);
+            so3_log(&mut d, &tmp);
+            assert_close(&[d[0] / h, d[1] / h, d[2] / h], &jl[k * 3..k * 3 + 3], 1e-5f64);
+            ::mul::mul(&mut tmp, &rw_t, &rd);
+            so3_log(&mut d, &tmp);
+            assert_close(&[d[0] / h, d[1] / h, d[2] / h], &jr[k * 3..k * 3 + 3], 1e-5f64);
+
+            let e = [if k == 0 {1f64} else {0f64}, if k == 1 {1f64} else {0f64}, if k == 2 {1f64} else {0f64}];
+            assert_close(&mul3(&inv, &mul3(&jl, &e)), &e, 1e-12f64);
+        }
+    }
+}
+
+/// The 4x4 matrix `[[hat(w), v], [0, 0]]` of the twist `[v, w]`.
+#[inline]
+pub fn hat<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], xi: &'b [T; 6]) -> &'a mut [T; 16] {
+    let mut w = [T::zero(); 9];
+    so3_hat(&mut w, &[xi[3], xi[4], xi[5]]);
+    for j in 0..3 {
+        for i in 0..3 {
+            out[j * 4 + i] = w[j * 3 + i];
+        }
+        out[j * 4 + 3] = T::zero();
+        out[12 + j] = xi[j];
+    }
+    out[15] = T::zero();
+    out
+}
+
+#[inline]
+pub fn vee<'a, 'b, T: Copy + Num>(out: &'a mut [T; 6], m: &'b [T; 16]) -> &'a mut [T; 6] {
+    out[0] = m[12];
+    out[1] = m[13];
+    out[2] = m[14];
+    out[3] = m[6];
+    out[4] = m[8];
+    out[5] = m[1];
+    out
+}
+
+/// Rigid transform reached by following the twist `xi` for unit time.
+#[inline]
+pub fn se3_exp<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], xi: &'b [T; 6]) -> &'a mut [T; 16] {
+    let w = [xi[3], xi[4], xi[5]];
+    let mut j = [T::zero(); 9];
+    so3_left_jacobian(&mut j, &w);
+    let t = mul3(&j, &[xi[0], xi[1], xi[2]]);
+
+    so3_exp(out, &w);
+    out[12] = t[0];
+    out[13] = t[1];
+    out[14] = t[2];
+    out
+}
+
+/// Twist of the rigid transform `m`, the inverse of `se3_exp` for rotations
+/// of at most half a turn.
+#[inline]
+pub fn se3_log<'a, 'b, T: Copy + Num>(out: &'a mut [T; 6], m: &'b [T; 16]) -> &'a mut [T; 6] {
+    let mut w = [T::zero(); 3];
+    so3_log(&mut w, m);
+    let mut inv = [T::zero(); 9];
+    so3_left_jacobian_inverse(&mut inv, &w);
+    let v = mul3(&inv, &[m[12], m[13], m[14]]);
+
+    out[0] = v[0];
+    out[1] = v[1];
+    out[2] = v[2];
+    out[3] = w[0];
+    out[4] = w[1];
+    out[5] = w[2];
+    out
+}
+#[cfg(test)]
+fn exp_series(xi: &[f64; 6]) -> [f64; 16] {
+    let mut a = [0f64; 16];
+    hat(&mut a, xi);
+    let mut sum = ::create::new_identity::<f64>();
+    let mut term = ::create::new_identity::<f64>();
+    let mut tmp = [0f64; 16];
+    for k in 1..60 {
+        ::mul::mul(&mut tmp, &term, &a);
+        ::mul::smul(&mut term, &tmp, 1f64 / k as f64);
+        for i in 0..16 {
+            sum[i] += term[i];
+        }
+    }
+    sum
+}
+#[test]
+fn test_se3_exp_log() {
+    let mut rng = Rng(137);
+    let mut m = [0f64; 16];
+    let mut xi = [0f64; 6];
+    for i in 0..20 {
+        let q = rng.quat();
+        let s = if i < 10 {2f64} else {1e-5f64};
+        let twist = [rng.next() * 5f64, rng.next() * 5f64, rng.next() * 5f64, q[0] * s, q[1] * s, q[2] * s];
+        se3_exp(&mut m, &twist);
+        assert_close(&m, &exp_series(&twist), 1e-12f64);
+        se3_log(&mut xi, &m);
+        assert_close(&xi, &twist, 1e-12f64);
+
+        let mut h = [0f64; 16];
+        let mut back = [0f64; 6];
+        hat(&mut h, &twist);
+        vee(&mut back, &h);
+        assert_eq!(back, twist);
+    }
+
+    se3_exp(&mut m, &[1f64, 2f64, 3f64, 0f64, 0f64, 0f64]);
+    assert_close(&m[12..15], &[1f64, 2f64, 3f64], 0f64);
+
+    // a screw: motion along the rotation axis is not swept around it
+    se3_exp(&mut m, &[0f64, 0f64, 1f64, 0f64, 0f64, ::core::f64::consts::PI]);
+    assert_close(&m, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, -1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 1f64, 1f64
+    ], 1e-15f64);
+}
+
+/// Adjoint of the pose `m` (rotation `R`, translation `t`), mapping twists in
+/// its local frame to the parent frame: `[[R, hat(t) R], [0, R]]`. Apply its
+/// transpose to wrenches, or `A * C * A^T` to a 6x6 covariance.
+#[inline]
+pub fn adjoint<'a, 'b, T: Copy + Num>(out: &'a mut [T; 36], m: &'b [T; 16]) -> &'a mut [T; 36] {
+    let r = [m[0], m[1], m[2], m[4], m[5], m[6], m[8], m[9], m[10]];
+    let mut t = [T::zero(); 9];
+    so3_hat(&mut t, &[m[12], m[13], m[14]]);
+    let mut tr = [T::zero(); 9];
+    for j in 0..3 {
+        let c = mul3(&t, &[r[j * 3], r[j * 3 + 1], r[j * 3 + 2]]);
+        tr[j * 3] = c[0];
+        tr[j * 3 + 1] = c[1];
+        tr[j * 3 + 2] = c[2];
+    }
+
+    set_block(out, 0, 0, &r);
+    set_block(out, 0, 1, &tr);
+    set_block(out, 1, 0, &[T::zero(); 9]);
+    set_block(out, 1, 1, &r);
+    out
+}
+#[cfg(test)]
+fn mul6(m: &[f64; 36], v: &[f64; 6]) -> [f64; 6] {
+    let mut out = [0f64; 6];
+    for j in 0..6 {
+        for i in 0..6 {
+            out[i] += m[j * 6 + i] * v[j];
+        }
+    }
+    out
+}
+#[test]
+fn test_adjoint() {
+    let mut rng = Rng(139);
+    let mut ad = [0f64; 36];
+    let mut inv = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    let mut lhs = [0f64; 16];
+    let mut rhs = [0f64; 16];
+    for i in 0..10 {
+        let m = if i == 0 {
+            let mut view = [0f64; 16];
+            ::transform::look_at(&mut view, &[3f64, 4f64, 5f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+            view
+        } else {
+            rng.trs(true)
+        };
+        let q = rng.quat();
+        let xi = [rng.next(), rng.next(), rng.next(), q[0], q[1], q[2]];
+
+        // m * exp(xi) * m^-1 == exp(Ad(m) xi)
+        adjoint(&mut ad, &m);
+        se3_exp(&mut lhs, &xi);
+        ::mul::mul(&mut tmp, &m, &lhs);
+        ::misc::inverse_rigid(&mut inv, &m);
+        ::mul::mul(&mut lhs, &tmp, &inv);
+        se3_exp(&mut rhs, &mul6(&ad, &xi));
+        assert_close(&lhs, &rhs, 1e-12f64);
+    }
+
+    // a pure translation by (1, 2, 3): [[I, hat(t)], [0, I]]
+    let mut m = ::create::new_identity::<f64>();
+    m[12] = 1f64;
+    m[13] = 2f64;
+    m[14] = 3f64;
+    adjoint(&mut ad, &m);
+    assert_eq!(&ad[..], &[
+        1f64, 0f64, 0f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64, 0f64, 0f64,
+        0f64, 3f64, -2f64, 1f64, 0f64, 0f64,
+        -3f64, 0f64, 1f64, 0f64, 1f64, 0f64,
+        2f64, -1f64, 0f64, 0f64, 0f64, 1f64
+    ][..]);
+}
+
+/// SE(3) left Jacobian: `exp(xi + d) ~ exp(J d) * exp(xi)` for small `d`.
+#[inline]
+pub fn se3_left_jacobian<'a, 'b, T: Copy + Num>(out: &'a mut [T; 36], xi: &'b [T; 6]) -> &'a mut [T; 36] {
+    let one = T::one();
+    let v = [xi[0], xi[1], xi[2]];
+    let w = [xi[3], xi[4], xi[5]];
+    let theta2 = w[0] * w[0] + w[1] * w[1] + w[2] * w[2];
+
+    let (c1, c2, c3) = if theta2 <= epsilon::<T>().sqrt().sqrt() {
+        (
+            one / T::from_isize(6isize) - theta2 / T::from_isize(120isize) + theta2 * theta2 / T::from_isize(5040isize),
+            one / T::from_isize(24isize) - theta2 / T::from_isize(720isize) + theta2 * theta2 / T::from_isize(40320isize),
+            one / T::from_isize(120isize) - theta2 / T::from_isize(2520isize) + theta2 * theta2 / T::from_isize(120960isize),
+        )
+    } else {
+        let theta = theta2.sqrt();
+        let s = theta.sin();
+        let c = theta.cos();
+        let theta4 = theta2 * theta2;
+        let two = T::from_isize(2isize);
+        (
+            (theta - s) / (theta2 * theta),
+            (theta2 + two * c - two) / (two * theta4),
+            (two * theta - T::from_isize(3isize) * s + theta * c) / (two * theta4 * theta),
+        )
+    };
+
+    // Q = v^/2 + c1 (w^v^ + v^w^ + w^v^w^) + c2 (w^w^v^ + v^w^w^ - 3 w^v^w^)
+    //   + c3 (w^v^w^w^ + w^w^v^w^)
+    let mut wh = [T::zero(); 16];
+    let mut vh = [T::zero(); 16];
+    let mut tmp = [T::zero(); 16];
+    ::set::zero(&mut wh);
+    ::set::zero(&mut vh);
+    let mut w9 = [T::zero(); 9];
+    so3_hat(&mut w9, &w);
+    for j in 0..3 {
+        for i in 0..3 {
+            wh[j * 4 + i] = w9[j * 3 + i];
+        }
+    }
+    so3_hat(&mut w9, &v);
+    for j in 0..3 {
+        for i in 0..3 {
+            vh[j * 4 + i] = w9[j * 3 + i];
+        }
+    }
+    let product = |factors: &[&[T; 16]]| {
+        let mut out = *factors[0];
+        let mut tmp = [T::zero(); 16];
+        for f in factors[1..].iter() {
+            ::mul::mul(&mut tmp, &out, f);
+            out = tmp;
+        }
+        out
+    };
+    let wv = product(&[&wh, &vh]);
+    let vw = product(&[&vh, &wh]);
+    let wvw = product(&[&wh, &vh, &wh]);
+    let wwv = product(&[&wh, &wh, &vh]);
+    let vww = product(&[&vh, &wh, &wh]);
+    let wvww = product(&[&wh, &vh, &wh, &wh]);
+    let wwvw = product(&[&wh, &wh, &vh, &wh]);
+    let half = T::from_f32(0.5f32);
+    let three = T::from_isize(3isize);
+    for k in 0..16 {
+        tmp[k] = half * vh[k] + c1 * (wv[k] + vw[k] + wvw[k]) + c2 * (wwv[k] + vww[k] - three * wvw[k]) + c3 * (wvww[k] + wwvw[k]);
+    }
+    let q = [tmp[0], tmp[1], tmp[2], tmp[4], tmp[5], tmp[6], tmp[8], tmp[9], tmp[10]];
+
+    let mut j = [T::zero(); 9];
+    so3_left_jacobian(&mut j, &w);
+    set_block(out, 0, 0, &j);
+    set_block(out, 0, 1, &q);
+    set_block(out, 1, 0, &[T::zero(); 9]);
+    set_block(out, 1, 1, &j);
+    out
+}
+
+/// SE(3) right Jacobian: `exp(xi + d) ~ exp(xi) * exp(J d)` for small `d`.
+#[inline]
+pub fn se3_right_jacobian<'a, 'b, T: Copy + Num>(out: &'a mut [T; 36], xi: &'b [T; 6]) -> &'a mut [T; 36] {
+    se3_left_jacobian(out, &[neg(xi[0]), neg(xi[1]), neg(xi[2]), neg(xi[3]), neg(xi[4]), neg(xi[5])])
+}
+#[test]
+fn test_se3_jacobians() {
+    let mut rng = Rng(149);
+    let h = 1e-6f64;
+    let mut jl = [0f64; 36];
+    let mut jr = [0f64; 36];
+    let mut m = [0f64; 16];
+    let mut inv = [0f64; 16];
+    let mut md = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    let mut d = [0f64; 6];
+    for i in 0..10 {
+        let q = rng.quat();
+        let s = if i < 8 {2f64} else {1e-3f64};
+        let xi = [rng.next() * 3f64, rng.next() * 3f64, rng.next() * 3f64, q[0] * s, q[1] * s, q[2] * s];
+        se3_left_jacobian(&mut jl, &xi);
+        se3_right_jacobian(&mut jr, &xi);
+        se3_exp(&mut m, &xi);
+        ::misc::inverse_rigid(&mut inv, &m);
+
+        for k in 0..6 {
+            let mut xd = xi;
+            xd[k] += h;
+            se3_exp(&mut md, &xd);
+
+            ::mul::mul(&mut tmp, &md, &inv);
+            se3_log(&mut d, &tmp);
+            let col: [f64; 6] = [d[0] / h, d[1] / h, d[2] / h, d[3] / h, d[4] / h, d[5] / h];
+            assert_close(&col, &jl[k * 6..k * 6 + 6], 1e-5f64);
+
+            ::mul::mul(&mut tmp, &inv, &md);
+            se3_log(&mut d, &tmp);
+            let col: [f64; 6] = [d[0] / h, d[1] / h, d[2] / h, d[3] / h, d[4] / h, d[5] / h];
+            assert_close(&col, &jr[k * 6..k * 6 + 6], 1e-5f64);
+        }
+    }
+}
@@ -0,0 +1,490 @@
+//! Explicit SIMD kernels for `f32` on x86_64, reached through the `Kernel`
+//! trait. `mul_simd`, `inverse_simd` and `transpose_simd`, and with them the
+//! `Mat4` product, `inverse` and `transpose`, take any `Kernel` type and pick
+//! these for `f32` without specialization; `mul::mul`, `misc::inverse` and
+//! `misc::transpose` stay generic over `Num` and always run the scalar code.
+//!
+//! SSE2 is part of the x86_64 baseline and is always available. `inverse`
+//! gathers its operands with SSE4.1 blends and `mul` uses AVX, each when
+//! compiled with the matching `target-feature`, or when the `std` feature is
+//! enabled and the CPU reports it at runtime. SSE4.1's dot product sums in a
+//! different order from the scalar code, so `mul` does not use it.
+//!
+//! Every kernel performs the same IEEE operations in the same order as the
+//! scalar functions in `mul` and `misc` (no FMA, no reciprocal estimates), so
+//! the results are identical to the scalar path: the ULP bound is 0.
+
+use num::Num;
+
+use misc;
+use mul;
+#[cfg(test)]
+use test_util::Rng;
+
+
+/// The element types `Mat4` multiplies, inverts and transposes. Every method
+/// defaults to the scalar function, so another `Num` type opts in with an
+/// empty `impl Kernel for MyNum {}`.
+pub trait Kernel: Copy + Num {
+    #[inline]
+    fn mul<'a, 'b>(out: &'a mut [Self; 16], a: &'b [Self; 16], b: &'b [Self; 16]) -> &'a mut [Self; 16] {
+        mul::mul(out, a, b)
+    }
+    #[inline]
+    fn inverse<'a, 'b>(out: &'a mut [Self; 16], a: &'b [Self; 16]) -> &'a mut [Self; 16] {
+        misc::inverse(out, a)
+    }
+    #[inline]
+    fn transpose<'a, 'b>(out: &'a mut [Self; 16], a: &'b [Self; 16]) -> &'a mut [Self; 16] {
+        misc::transpose(out, a)
+    }
+}
+
+/// `mul::mul`, on the SIMD path for `f32`.
+#[inline]
+pub fn mul_simd<'a, 'b, T: Kernel>(out: &'a mut [T; 16], a: &'b [T; 16], b: &'b [T; 16]) -> &'a mut [T; 16] {
+    <T as Kernel>::mul(out, a, b)
+}
+
+/// `misc::inverse`, on the SIMD path for `f32`.
+#[inline]
+pub fn inverse_simd<'a, 'b, T: Kernel>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    <T as Kernel>::inverse(out, a)
+}
+
+/// `misc::transpose`, on the SIMD path for `f32`.
+#[inline]
+pub fn transpose_simd<'a, 'b, T: Kernel>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    <T as Kernel>::transpose(out, a)
+}
+
+impl Kernel for f64 {}
+impl Kernel for i8 {}
+impl Kernel for i16 {}
+impl Kernel for i32 {}
+impl Kernel for i64 {}
+impl Kernel for isize {}
+impl Kernel for u8 {}
+impl Kernel for u16 {}
+impl Kernel for u32 {}
+impl Kernel for u64 {}
+impl Kernel for usize {}
+
+#[cfg(not(target_arch = "x86_64"))]
+impl Kernel for f32 {}
+
+#[cfg(target_arch = "x86_64")]
+impl Kernel for f32 {
+    #[inline]
+    fn mul<'a, 'b>(out: &'a mut [f32; 16], a: &'b [f32; 16], b: &'b [f32; 16]) -> &'a mut [f32; 16] {
+        x86::mul(out, a, b);
+        out
+    }
+    #[inline]
+    fn inverse<'a, 'b>(out: &'a mut [f32; 16], a: &'b [f32; 16]) -> &'a mut [f32; 16] {
+        x86::inverse(out, a);
+        out
+    }
+    #[inline]
+    fn transpose<'a, 'b>(out: &'a mut [f32; 16], a: &'b [f32; 16]) -> &'a mut [f32; 16] {
+        x86::transpose(out, a);
+        out
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    use set::identity;
+
+
+    #[inline]
+    pub fn mul(out: &mut [f32; 16], a: &[f32; 16], b: &[f32; 16]) {
+        #[cfg(target_feature = "avx")]
+        unsafe { mul_avx(out, a, b) }
+
+        #[cfg(all(not(target_feature = "avx"), feature = "std"))]
+        {
+            if is_x86_feature_detected!("avx") {
+                unsafe { mul_avx(out, a, b) }
+            } else {
+                mul_sse2(out, a, b)
+            }
+        }
+
+        #[cfg(all(not(target_feature = "avx"), not(feature = "std")))]
+        mul_sse2(out, a, b)
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn mul_sse2(out: &mut [f32; 16], a: &[f32; 16], b: &[f32; 16]) {
+        unsafe {
+            let a0 = _mm_loadu_ps(a.as_ptr());
+            let a1 = _mm_loadu_ps(a.as_ptr().add(4));
+            let a2 = _mm_loadu_ps(a.as_ptr().add(8));
+            let a3 = _mm_loadu_ps(a.as_ptr().add(12));
+
+            for i in 0..4 {
+                let j = i * 4;
+                let r = _mm_add_ps(
+                    _mm_add_ps(
+                        _mm_add_ps(
+                            _mm_mul_ps(_mm_set1_ps(b[j]), a0),
+                            _mm_mul_ps(_mm_set1_ps(b[j + 1]), a1)
+                        ),
+                        _mm_mul_ps(_mm_set1_ps(b[j + 2]), a2)
+                    ),
+                    _mm_mul_ps(_mm_set1_ps(b[j + 3]), a3)
+                );
+                _mm_storeu_ps(out.as_mut_ptr().add(j), r);
+            }
+        }
+    }
+
+    #[allow(dead_code)]
+    #[target_feature(enable = "avx")]
+    unsafe fn broadcast(x: __m128) -> __m256 {
+        _mm256_insertf128_ps(_mm256_castps128_ps256(x), x, 1)
+    }
+
+    #[allow(dead_code)]
+    #[target_feature(enable = "avx")]
+    pub unsafe fn mul_avx(out: &mut [f32; 16], a: &[f32; 16], b: &[f32; 16]) {
+        let a0 = broadcast(_mm_loadu_ps(a.as_ptr()));
+        let a1 = broadcast(_mm_loadu_ps(a.as_ptr().add(4)));
+        let a2 = broadcast(_mm_loadu_ps(a.as_ptr().add(8)));
+        let a3 = broadcast(_mm_loadu_ps(a.as_ptr().add(12)));
+
+        // two output columns per iteration, one in each 128 bit lane
+        for i in 0..2 {
+            let j = i * 8;
+            let bb = _mm256_loadu_ps(b.as_ptr().add(j));
+            let r = _mm256_add_ps(
+                _mm256_add_ps(
+                    _mm256_add_ps(
+                        _mm256_mul_ps(_mm256_permute_ps(bb, 0x00), a0),
+                        _mm256_mul_ps(_mm256_permute_ps(bb, 0x55), a1)
+                    ),
+                    _mm256_mul_ps(_mm256_permute_ps(bb, 0xAA), a2)
+                ),
+                _mm256_mul_ps(_mm256_permute_ps(bb, 0xFF), a3)
+            );
+            _mm256_storeu_ps(out.as_mut_ptr().add(j), r);
+        }
+    }
+
+    #[inline]
+    pub fn transpose(out: &mut [f32; 16], a: &[f32; 16]) {
+        unsafe {
+            let c0 = _mm_loadu_ps(a.as_ptr());
+            let c1 = _mm_loadu_ps(a.as_ptr().add(4));
+            let c2 = _mm_loadu_ps(a.as_ptr().add(8));
+            let c3 = _mm_loadu_ps(a.as_ptr().add(12));
+
+            let t0 = _mm_unpacklo_ps(c0, c1);
+            let t1 = _mm_unpacklo_ps(c2, c3);
+            let t2 = _mm_unpackhi_ps(c0, c1);
+            let t3 = _mm_unpackhi_ps(c2, c3);
+
+            _mm_storeu_ps(out.as_mut_ptr(), _mm_movelh_ps(t0, t1));
+            _mm_storeu_ps(out.as_mut_ptr().add(4), _mm_movehl_ps(t1, t0));
+            _mm_storeu_ps(out.as_mut_ptr().add(8), _mm_movelh_ps(t2, t3));
+            _mm_storeu_ps(out.as_mut_ptr().add(12), _mm_movehl_ps(t3, t2));
+        }
+    }
+
+    #[inline]
+    pub fn inverse(out: &mut [f32; 16], a: &[f32; 16]) {
+        #[cfg(target_feature = "sse4.1")]
+        unsafe { inverse_sse41(out, a) }
+
+        #[cfg(all(not(target_feature = "sse4.1"), feature = "std"))]
+        {
+            if is_x86_feature_detected!("sse4.1") {
+                unsafe { inverse_sse41(out, a) }
+            } else {
+                inverse_sse2(out, a)
+            }
+        }
+
+        #[cfg(all(not(target_feature = "sse4.1"), not(feature = "std")))]
+        inverse_sse2(out, a)
+    }
+
+    // `[m1x, m0y, m3x, m2y]` from the columns `c0..c3` of `m`, the pattern
+    // every first factor of the cofactor sums follows; SSE2 pairs the lanes
+    // up with one more shuffle where SSE4.1 blends them
+    macro_rules! gather_sse2 {
+        ($c0:expr, $c1:expr, $c2:expr, $c3:expr, $x:expr, $y:expr) => {
+            _mm_shuffle_ps(
+                _mm_shuffle_ps($c1, $c0, $x * 0x05 + $y * 0x50),
+                _mm_shuffle_ps($c3, $c2, $x * 0x05 + $y * 0x50),
+                0x88
+            )
+        };
+    }
+    macro_rules! gather_sse41 {
+        ($c0:expr, $c1:expr, $c2:expr, $c3:expr, $x:expr, $y:expr) => {
+            _mm_blend_ps(_mm_shuffle_ps($c1, $c3, $x * 0x55), _mm_shuffle_ps($c0, $c2, $y * 0x55), 0b1010)
+        };
+    }
+
+    // the cofactor expansion of `misc::inverse` on the loaded columns, with
+    // `$gather` picking the matrix operands
+    macro_rules! inverse {
+        ($out:expr, $a:expr, $gather:ident) => {{
+            let c0 = _mm_loadu_ps($a.as_ptr());
+            let c1 = _mm_loadu_ps($a.as_ptr().add(4));
+            let c2 = _mm_loadu_ps($a.as_ptr().add(8));
+            let c3 = _mm_loadu_ps($a.as_ptr().add(12));
+
+            // b00..b03, b04..b07 and b08..b11 of the scalar version; a shuffle
+            // mask holds two bits per lane from the lowest, so 0xB9 takes
+            // lanes [1, 2, 3, 2]
+            let b0 = _mm_sub_ps(
+                _mm_mul_ps(_mm_shuffle_ps(c0, c0, 0x40), _mm_shuffle_ps(c1, c1, 0xB9)),
+                _mm_mul_ps(_mm_shuffle_ps(c0, c0, 0xB9), _mm_shuffle_ps(c1, c1, 0x40))
+            );
+            let b1 = _mm_sub_ps(
+                _mm_mul_ps(_mm_shuffle_ps(c0, c2, 0x09), _mm_shuffle_ps(c1, c3, 0x9F)),
+                _mm_mul_ps(_mm_shuffle_ps(c0, c2, 0x9F), _mm_shuffle_ps(c1, c3, 0x09))
+            );
+            let b2 = _mm_sub_ps(
+                _mm_mul_ps(_mm_shuffle_ps(c2, c2, 0x94), _mm_shuffle_ps(c3, c3, 0xFB)),
+                _mm_mul_ps(_mm_shuffle_ps(c2, c2, 0xFB), _mm_shuffle_ps(c3, c3, 0x94))
+            );
+
+            let mut b = [0f32; 12];
+            _mm_storeu_ps(b.as_mut_ptr(), b0);
+            _mm_storeu_ps(b.as_mut_ptr().add(4), b1);
+            _mm_storeu_ps(b.as_mut_ptr().add(8), b2);
+            let d = b[0] * b[11] - b[1] * b[10] + b[2] * b[9] + b[3] * b[8] - b[4] * b[7] + b[5] * b[6];
+
+            if d == 0f32 {
+                identity($out);
+                return;
+            }
+            let inv_d = _mm_set1_ps(1f32 / d);
+
+            let neg = _mm_set1_ps(-0f32);
+            let neg_odd = _mm_setr_ps(0f32, -0f32, 0f32, -0f32);
+            let neg_even = _mm_setr_ps(-0f32, 0f32, -0f32, 0f32);
+
+            // the b factors of each sum, [b02, b02, b05, b05],
+            // [b04, b04, b02, b02] and [b07, b07, b09, b09]
+            let t1 = _mm_shuffle_ps(b0, b1, 0x5A);
+            let t2 = _mm_shuffle_ps(b1, b0, 0xA0);
+            let t3 = _mm_shuffle_ps(b1, b2, 0x5F);
+
+            // each output is ((p + q) + r) * inv_d with the scalar signs
+            // folded into the first factor, which rounds identically
+            let o0 = _mm_add_ps(
+                _mm_add_ps(
+                    _mm_mul_ps($gather!(c0, c1, c2, c3, 1, 2), _mm_shuffle_ps(b2, b1, 0x1B)),
+                    _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 2, 1), neg), _mm_shuffle_ps(b2, b1, 0x4E))
+                ),
+                _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 3, 3), neg_odd), _mm_shuffle_ps(b2, b0, 0xF5))
+            );
+            let o1 = _mm_add_ps(
+                _mm_add_ps(
+                    _mm_mul_ps($gather!(c0, c1, c2, c3, 2, 0), _mm_shuffle_ps(b2, t1, 0x8C)),
+                    _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 0, 2), neg), _mm_shuffle_ps(b2, t1, 0x23))
+                ),
+                _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 3, 3), neg_even), _mm_shuffle_ps(b1, b0, 0x5F))
+            );
+            let o2 = _mm_add_ps(
+                _mm_add_ps(
+                    _mm_mul_ps($gather!(c0, c1, c2, c3, 0, 1), _mm_shuffle_ps(b2, t2, 0x82)),
+                    _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 1, 0), neg), _mm_shuffle_ps(b2, t2, 0x28))
+                ),
+                _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 3, 3), neg_odd), _mm_shuffle_ps(b1, b0, 0x0A))
+            );
+            let o3 = _mm_add_ps(
+                _mm_add_ps(
+                    _mm_mul_ps($gather!(c0, c1, c2, c3, 1, 0), _mm_shuffle_ps(t3, b0, 0xD8)),
+                    _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 0, 1), neg), _mm_shuffle_ps(t3, b0, 0x72))
+                ),
+                _mm_mul_ps(_mm_xor_ps($gather!(c0, c1, c2, c3, 2, 2), neg_even), _mm_shuffle_ps(b1, b0, 0x0A))
+            );
+
+            _mm_storeu_ps($out.as_mut_ptr(), _mm_mul_ps(o0, inv_d));
+            _mm_storeu_ps($out.as_mut_ptr().add(4), _mm_mul_ps(o1, inv_d));
+            _mm_storeu_ps($out.as_mut_ptr().add(8), _mm_mul_ps(o2, inv_d));
+            _mm_storeu_ps($out.as_mut_ptr().add(12), _mm_mul_ps(o3, inv_d));
+        }};
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn inverse_sse2(out: &mut [f32; 16], a: &[f32; 16]) {
+        unsafe { inverse!(out, a, gather_sse2) }
+    }
+
+    /// `inverse_sse2` with the matrix operands gathered by blends.
+    #[allow(dead_code)]
+    #[target_feature(enable = "sse4.1")]
+    pub unsafe fn inverse_sse41(out: &mut [f32; 16], a: &[f32; 16]) {
+        inverse!(out, a, gather_sse41)
+    }
+}
+
+#[cfg(test)]
+fn random_f32(rng: &mut Rng) -> [f32; 16] {
+    let mut m = [0f32; 16];
+    for v in m.iter_mut() {
+        *v = (rng.next() * 100f64) as f32;
+    }
+    m
+}
+#[cfg(test)]
+fn assert_bits_eq(a: &[f32; 16], b: &[f32; 16]) {
+    for i in 0..16 {
+        assert_eq!(a[i].to_bits(), b[i].to_bits(), "{:?} != {:?}", a, b);
+    }
+}
+#[test]
+fn test_kernel_mul() {
+    let mut rng = Rng(19);
+    let mut a = [0f32; 16];
+    let mut b = [0f32; 16];
+    for _ in 0..1000 {
+        let x = random_f32(&mut rng);
+        let y = random_f32(&mut rng);
+        <f32 as Kernel>::mul(&mut a, &x, &y);
+        mul::mul(&mut b, &x, &y);
+        assert_bits_eq(&a, &b);
+        mul_simd(&mut a, &x, &y);
+        assert_bits_eq(&a, &b);
+    }
+    #[cfg(target_arch = "x86_64")]
+    {
+        let x = random_f32(&mut rng);
+        let y = random_f32(&mut rng);
+        mul::mul(&mut b, &x, &y);
+        x86::mul_sse2(&mut a, &x, &y);
+        assert_bits_eq(&a, &b);
+        #[cfg(feature = "std")]
+        {
+            if is_x86_feature_detected!("avx") {
+                unsafe { x86::mul_avx(&mut a, &x, &y) };
+                assert_bits_eq(&a, &b);
+            }
+        }
+    }
+
+    // translate * scale leaves the translation unscaled; the other order
+    // would double it
+    let mut t = ::create::new_identity::<f32>();
+    t[12] = 1f32;
+    t[13] = 2f32;
+    t[14] = 3f32;
+    let mut s = ::create::new_identity::<f32>();
+    s[0] = 2f32;
+    s[5] = 2f32;
+    s[10] = 2f32;
+    <f32 as Kernel>::mul(&mut a, &t, &s);
+    assert_bits_eq(&a, &[
+        2f32, 0f32, 0f32, 0f32,
+        0f32, 2f32, 0f32, 0f32,
+        0f32, 0f32, 2f32, 0f32,
+        1f32, 2f32, 3f32, 1f32
+    ]);
+}
+#[cfg(all(target_arch = "x86_64", target_feature = "avx"))]
+#[test]
+fn test_kernel_mul_avx() {
+    let mut rng = Rng(31);
+    let mut a = [0f32; 16];
+    let mut b = [0f32; 16];
+    for _ in 0..1000 {
+        let x = random_f32(&mut rng);
+        let y = random_f32(&mut rng);
+        unsafe { x86::mul_avx(&mut a, &x, &y) };
+        mul::mul(&mut b, &x, &y);
+        assert_bits_eq(&a, &b);
+    }
+}
+#[test]
+fn test_kernel_inverse() {
+    let mut rng = Rng(23);
+    let mut a = [0f32; 16];
+    let mut b = [0f32; 16];
+    for _ in 0..1000 {
+        let x = random_f32(&mut rng);
+        <f32 as Kernel>::inverse(&mut a, &x);
+        misc::inverse(&mut b, &x);
+        assert_bits_eq(&a, &b);
+        inverse_simd(&mut a, &x);
+        assert_bits_eq(&a, &b);
+        #[cfg(target_arch = "x86_64")]
+        {
+            x86::inverse_sse2(&mut a, &x);
+            assert_bits_eq(&a, &b);
+            #[cfg(feature = "std")]
+            {
+                if is_x86_feature_detected!("sse4.1") {
+                    unsafe { x86::inverse_sse41(&mut a, &x) };
+                    assert_bits_eq(&a, &b);
+                }
+            }
+        }
+    }
+    <f32 as Kernel>::inverse(&mut a, &[1f32; 16]);
+    assert_bits_eq(&a, &::create::new_identity());
+
+    <f32 as Kernel>::inverse(&mut a, &[
+        2f32, 0f32, 0f32, 0f32,
+        0f32, 2f32, 0f32, 0f32,
+        0f32, 0f32, 2f32, 0f32,
+        1f32, 2f32, 3f32, 1f32
+    ]);
+    assert_bits_eq(&a, &[
+        0.5f32, 0f32, 0f32, 0f32,
+        0f32, 0.5f32, 0f32, 0f32,
+        0f32, 0f32, 0.5f32, 0f32,
+        -0.5f32, -1f32, -1.5f32, 1f32
+    ]);
+}
+#[cfg(all(target_arch = "x86_64", target_feature = "sse4.1"))]
+#[test]
+fn test_kernel_inverse_sse41() {
+    let mut rng = Rng(37);
+    let mut a = [0f32; 16];
+    let mut b = [0f32; 16];
+    for _ in 0..1000 {
+        let x = random_f32(&mut rng);
+        unsafe { x86::inverse_sse41(&mut a, &x) };
+        misc::inverse(&mut b, &x);
+        assert_bits_eq(&a, &b);
+    }
+    unsafe { x86::inverse_sse41(&mut a, &[1f32; 16]) };
+    assert_bits_eq(&a, &::create::new_identity());
+}
+#[test]
+fn test_kernel_transpose() {
+    let mut rng = Rng(29);
+    let mut a = [0f32; 16];
+    let mut b = [0f32; 16];
+    for _ in 0..100 {
+        let x = random_f32(&mut rng);
+        <f32 as Kernel>::transpose(&mut a, &x);
+        misc::transpose(&mut b, &x);
+        assert_bits_eq(&a, &b);
+        transpose_simd(&mut a, &x);
+        assert_bits_eq(&a, &b);
+    }
+
+    let mut x = [0f32; 16];
+    for (i, v) in x.iter_mut().enumerate() {
+        *v = i as f32;
+    }
+    <f32 as Kernel>::transpose(&mut a, &x);
+    assert_bits_eq(&a, &[
+        0f32, 4f32, 8f32, 12f32,
+        1f32, 5f32, 9f32, 13f32,
+        2f32, 6f32, 10f32, 14f32,
+        3f32, 7f32, 11f32, 15f32
+    ]);
+}
@@ -0,0 +1,45 @@
+use transform::compose;
+use xorshift;
+
+
+pub struct Rng(pub u32);
+
+impl Rng {
+    pub fn next(&mut self) -> f64 {
+        (xorshift::next(&mut self.0) as f64) / (u32::MAX as f64) * 2f64 - 1f64
+    }
+
+    pub fn quat(&mut self) -> [f64; 4] {
+        let q = [self.next(), self.next(), self.next(), self.next()];
+        let l = (q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]).sqrt();
+        [q[0] / l, q[1] / l, q[2] / l, q[3] / l]
+    }
+
+    pub fn mat4(&mut self) -> [f64; 16] {
+        let mut m = [0f64; 16];
+        for v in m.iter_mut() {
+            *v = self.next();
+        }
+        m
+    }
+
+    pub fn trs(&mut self, uniform: bool) -> [f64; 16] {
+        let q = self.quat();
+        let p = [self.next() * 10f64, self.next() * 10f64, self.next() * 10f64];
+        let s = if uniform {
+            [1f64, 1f64, 1f64]
+        } else {
+            [self.next() * 2f64 + 2.5f64, self.next() * 2f64 - 2.5f64, self.next() * 2f64 + 2.5f64]
+        };
+        let mut m = [0f64; 16];
+        compose(&mut m, &p, &s, &q);
+        m
+    }
+}
+
+pub fn assert_close(a: &[f64], b: &[f64], eps: f64) {
+    assert_eq!(a.len(), b.len());
+    for i in 0..a.len() {
+        assert!((a[i] - b[i]).abs() <= eps, "{:?} != {:?}", a, b);
+    }
+}
@@ -1,93 +1,331 @@
-use core::f64::EPSILON;
-
 use num::Num;
 use signed::Signed;
 use vec3;
 
+use clip::ClipSpace;
 use create::{clone, new_identity};
+use decomposition::svd3;
+use misc::epsilon;
 use set::identity;
+#[cfg(test)]
+use test_util::{assert_close, Rng};
 
 
 #[inline]
 pub fn look_at<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], target: &'b [T; 3], up: &'b [T; 3]) -> &'a mut [T; 16] {
-    let eyex = eye[0];
-    let eyey = eye[1];
-    let eyez = eye[2];
-    let upx = up[0];
-    let upy = up[1];
-    let upz = up[2];
-    let targetx = target[0];
-    let targety = target[1];
-    let targetz = target[2];
-    let e = T::from_f64(EPSILON);
-
-    let mut z0 = eyex - targetx;
-    let mut z1 = eyey - targety;
-    let mut z2 = eyez - targetz;
-
-    if  z0.abs() < e &&
-        z1.abs() < e &&
-        z2.abs() < e
-     {
-        identity(out)
+    look_to(out, eye, &[target[0] - eye[0], target[1] - eye[1], target[2] - eye[2]], up)
+}
+#[test]
+fn test_look_at() {
+    let mut view = [0f64; 16];
+
+    // gluLookAt(1, 2, 3, 0, 0, 0, 0, 1, 0)
+    let (a, b, c) = (10f64.sqrt(), 35f64.sqrt(), 14f64.sqrt());
+    look_at(&mut view, &[1f64, 2f64, 3f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    assert_close(&view, &[
+        3f64 / a, -1f64 / b, 1f64 / c, 0f64,
+        0f64, 5f64 / b, 2f64 / c, 0f64,
+        -1f64 / a, -3f64 / b, 3f64 / c, 0f64,
+        0f64, 0f64, -c, 1f64
+    ], 1e-12f64);
+
+    look_at(&mut view, &[5f64, 0f64, 0f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    assert_close(&view, &[
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, -5f64, 1f64
+    ], 1e-12f64);
+
+    // the eye maps to the origin and the target onto -Z
+    let mut p = [0f64; 3];
+    look_at(&mut view, &[3f64, 4f64, 5f64], &[1f64, 1f64, 1f64], &[0f64, 1f64, 0f64]);
+    ::point::transform_point3(&mut p, &view, &[3f64, 4f64, 5f64]);
+    assert_close(&p, &[0f64, 0f64, 0f64], 1e-12f64);
+    ::point::transform_point3(&mut p, &view, &[1f64, 1f64, 1f64]);
+    assert_close(&p, &[0f64, 0f64, -29f64.sqrt()], 1e-12f64);
+}
+
+#[inline]
+pub fn look_at_rh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], target: &'b [T; 3], up: &'b [T; 3]) -> &'a mut [T; 16] {
+    look_at(out, eye, target, up)
+}
+
+/// Left-handed `look_at`: the camera looks down its +Z axis.
+#[inline]
+pub fn look_at_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], target: &'b [T; 3], up: &'b [T; 3]) -> &'a mut [T; 16] {
+    look_to(out, eye, &[eye[0] - target[0], eye[1] - target[1], eye[2] - target[2]], up)
+}
+#[cfg(test)]
+const MIRROR_Z: [f64; 16] = [
+    1f64, 0f64, 0f64, 0f64,
+    0f64, 1f64, 0f64, 0f64,
+    0f64, 0f64, -1f64, 0f64,
+    0f64, 0f64, 0f64, 1f64
+];
+#[test]
+fn test_look_at_lh() {
+    let mut rng = Rng(31);
+    let mut lh = [0f64; 16];
+    let mut rh = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    let mut mirrored = [0f64; 16];
+    for _ in 0..20 {
+        let e = rng.quat();
+        let t = rng.quat();
+        let u = rng.quat();
+        look_at_lh(&mut lh, &[e[0], e[1], e[2]], &[t[0], t[1], t[2]], &[u[0], u[1], u[2]]);
+        look_at_rh(&mut rh, &[e[0], e[1], -e[2]], &[t[0], t[1], -t[2]], &[u[0], u[1], -u[2]]);
+        ::mul::mul(&mut tmp, &MIRROR_Z, &rh);
+        ::mul::mul(&mut mirrored, &tmp, &MIRROR_Z);
+        assert_close(&lh, &mirrored, 1e-12f64);
+    }
+
+    look_at_lh(&mut lh, &[0f64, 0f64, -5f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    assert_close(&lh, &[
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 5f64, 1f64
+    ], 1e-12f64);
+}
+
+/// Degenerate inputs reported by `try_look_at` and `try_look_to`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LookAtError {
+    /// `eye` equals `target`, or `direction` is zero.
+    EyeAtTarget,
+    /// `up` is zero or parallel to the view direction.
+    UpParallel,
+}
+
+#[inline]
+fn view_z<'b, T: Copy + Signed>(direction: &'b [T; 3]) -> Option<[T; 3]> {
+    let e = T::from_f64(f64::EPSILON);
+    let z0 = -direction[0];
+    let z1 = -direction[1];
+    let z2 = -direction[2];
+
+    if z0.abs() < e && z1.abs() < e && z2.abs() < e {
+        None
     } else {
-        let mut len = T::one() / (z0 * z0 + z1 * z1 + z2 * z2).sqrt();
-        z0 = z0 * len;
-        z1 = z1 * len;
-        z2 = z2 * len;
-
-        let mut x0 = upy * z2 - upz * z1;
-        let mut x1 = upz * z0 - upx * z2;
-        let mut x2 = upx * z1 - upy * z0;
-        len = (x0 * x0 + x1 * x1 + x2 * x2).sqrt();
-        if len == T::zero() {
-            x0 = T::zero();
-            x1 = T::zero();
-            x2 = T::zero();
-        } else {
-            len = T::one() / len;
-            x0 = x0 * len;
-            x1 = x1 * len;
-            x2 = x2 * len;
-        }
+        let len = T::one() / (z0 * z0 + z1 * z1 + z2 * z2).sqrt();
+        Some([z0 * len, z1 * len, z2 * len])
+    }
+}
 
-        let mut y0 = z1 * x2 - z2 * x1;
-        let mut y1 = z2 * x0 - z0 * x2;
-        let mut y2 = z0 * x1 - z1 * x0;
-
-        len = (y0 * y0 + y1 * y1 + y2 * y2).sqrt();
-        if len == T::zero() {
-            y0 = T::zero();
-            y1 = T::zero();
-            y2 = T::zero();
-        } else {
-            len = T::one() / len;
-            y0 = y0 * len;
-            y1 = y1 * len;
-            y2 = y2 * len;
-        }
+#[inline]
+fn view_x<'b, T: Copy + Signed>(z: &'b [T; 3], up: &'b [T; 3]) -> Option<[T; 3]> {
+    let x0 = up[1] * z[2] - up[2] * z[1];
+    let x1 = up[2] * z[0] - up[0] * z[2];
+    let x2 = up[0] * z[1] - up[1] * z[0];
+    let len = (x0 * x0 + x1 * x1 + x2 * x2).sqrt();
+    let up_len = (up[0] * up[0] + up[1] * up[1] + up[2] * up[2]).sqrt();
 
-        out[0] = x0;
-        out[1] = x1;
-        out[2] = x2;
-        out[3] = T::zero();
-        out[4] = y0;
-        out[5] = y1;
-        out[6] = y2;
-        out[7] = T::zero();
-        out[8] = z0;
-        out[9] = z1;
-        out[10] = z2;
-        out[11] = T::zero();
-        out[12] = -(x0 * eyex + x1 * eyey + x2 * eyez);
-        out[13] = -(y0 * eyex + y1 * eyey + y2 * eyez);
-        out[14] = -(z0 * eyex + z1 * eyey + z2 * eyez);
-        out[15] = T::one();
+    if len == T::zero() || len <= T::from_isize(16isize) * epsilon::<T>() * up_len {
+        None
+    } else {
+        let len = T::one() / len;
+        Some([x0 * len, x1 * len, x2 * len])
+    }
+}
+
+// the world axis least aligned with `z`, ties going to x, then y
+#[inline]
+fn view_x_fallback<'b, T: Copy + Signed>(z: &'b [T; 3]) -> [T; 3] {
+    let ax = z[0].abs();
+    let ay = z[1].abs();
+    let az = z[2].abs();
+    let o = T::one();
+    let n = T::zero();
+    let axis = if ax <= ay && ax <= az {
+        [o, n, n]
+    } else if ay <= az {
+        [n, o, n]
+    } else {
+        [n, n, o]
+    };
 
-        return out;
+    match view_x(z, &axis) {
+        Some(x) => x,
+        None => [o, n, n],
     }
 }
 
+#[inline]
+fn set_view<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], x: &'b [T; 3], z: &'b [T; 3]) -> &'a mut [T; 16] {
+    let (x0, x1, x2) = (x[0], x[1], x[2]);
+    let (z0, z1, z2) = (z[0], z[1], z[2]);
+    let y0 = z1 * x2 - z2 * x1;
+    let y1 = z2 * x0 - z0 * x2;
+    let y2 = z0 * x1 - z1 * x0;
+
+    out[0] = x0;
+    out[1] = y0;
+    out[2] = z0;
+    out[3] = T::zero();
+    out[4] = x1;
+    out[5] = y1;
+    out[6] = z1;
+    out[7] = T::zero();
+    out[8] = x2;
+    out[9] = y2;
+    out[10] = z2;
+    out[11] = T::zero();
+    out[12] = -(x0 * eye[0] + x1 * eye[1] + x2 * eye[2]);
+    out[13] = -(y0 * eye[0] + y1 * eye[1] + y2 * eye[2]);
+    out[14] = -(z0 * eye[0] + z1 * eye[1] + z2 * eye[2]);
+    out[15] = T::one();
+    out
+}
+
+/// `look_at` for a viewing `direction` instead of a target point.
+///
+/// A zero `direction` gives identity. When `up` is zero or parallel to
+/// `direction` the world axis least aligned with the view is used instead.
+#[inline]
+pub fn look_to<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], direction: &'b [T; 3], up: &'b [T; 3]) -> &'a mut [T; 16] {
+    match view_z(direction) {
+        None => identity(out),
+        Some(z) => {
+            let x = match view_x(&z, up) {
+                Some(x) => x,
+                None => view_x_fallback(&z),
+            };
+            set_view(out, eye, &x, &z)
+        },
+    }
+}
+#[test]
+fn test_look_at_degenerate() {
+    let mut view = [0f64; 16];
+    let mut p = [0f64; 3];
+
+    // straight overhead: still a rigid, invertible view
+    let mut inv = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    look_at(&mut view, &[0f64, 10f64, 0f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    assert!(::misc::is_rigid(&view, 1e-12f64));
+    ::misc::inverse(&mut inv, &view);
+    ::mul::mul(&mut tmp, &view, &inv);
+    assert_close(&tmp, &new_identity::<f64>(), 1e-12f64);
+    ::point::transform_point3(&mut p, &view, &[0f64, 0f64, 0f64]);
+    assert_close(&p, &[0f64, 0f64, -10f64], 1e-12f64);
+
+    look_at(&mut view, &[1f64, 1f64, 1f64], &[1f64, 1f64, 1f64], &[0f64, 1f64, 0f64]);
+    assert_close(&view, &new_identity::<f64>(), 0f64);
+}
+
+/// `look_to` that stays continuous with the `previous` view matrix. When `up`
+/// is parallel to `direction` the previous camera up axis is used, and a zero
+/// `direction` keeps the previous orientation.
+#[inline]
+pub fn look_to_continuous<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], direction: &'b [T; 3], up: &'b [T; 3], previous: &'b [T; 16]) -> &'a mut [T; 16] {
+    match view_z(direction) {
+        None => {
+            let x = [previous[0], previous[4], previous[8]];
+            let z = [previous[2], previous[6], previous[10]];
+            set_view(out, eye, &x, &z)
+        },
+        Some(z) => {
+            let x = match view_x(&z, up) {
+                Some(x) => x,
+                None => match view_x(&z, &[previous[1], previous[5], previous[9]]) {
+                    Some(x) => x,
+                    None => view_x_fallback(&z),
+                },
+            };
+            set_view(out, eye, &x, &z)
+        },
+    }
+}
+
+#[inline]
+pub fn look_at_continuous<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], target: &'b [T; 3], up: &'b [T; 3], previous: &'b [T; 16]) -> &'a mut [T; 16] {
+    look_to_continuous(out, eye, &[target[0] - eye[0], target[1] - eye[1], target[2] - eye[2]], up, previous)
+}
+#[test]
+fn test_look_at_continuous() {
+    let up = [0f64, 1f64, 0f64];
+    let mut previous = [0f64; 16];
+    let mut view = [0f64; 16];
+
+    // approaching the pole from any side keeps the heading
+    for i in 0..8 {
+        let angle = i as f64 * 0.8f64;
+        let eye = [1e-3f64 * angle.cos(), 10f64, 1e-3f64 * angle.sin()];
+        look_at(&mut previous, &eye, &[0f64, 0f64, 0f64], &up);
+        look_to_continuous(&mut view, &[0f64, 10f64, 0f64], &[0f64, -1f64, 0f64], &up, &previous);
+        assert!(::misc::is_rigid(&view, 1e-12f64));
+        assert_close(&view[..12], &previous[..12], 1e-3f64);
+    }
+
+    // eye at target keeps the previous orientation
+    look_at_continuous(&mut view, &[1f64, 2f64, 3f64], &[1f64, 2f64, 3f64], &up, &previous);
+    assert_close(&view[..12], &previous[..12], 1e-12f64);
+    let mut p = [0f64; 3];
+    ::point::transform_point3(&mut p, &view, &[1f64, 2f64, 3f64]);
+    assert_close(&p, &[0f64, 0f64, 0f64], 1e-12f64);
+}
+
+/// `look_to` that reports degenerate input instead of falling back.
+#[inline]
+pub fn try_look_to<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], direction: &'b [T; 3], up: &'b [T; 3]) -> Result<&'a mut [T; 16], LookAtError> {
+    let z = match view_z(direction) {
+        Some(z) => z,
+        None => return Err(LookAtError::EyeAtTarget),
+    };
+    match view_x(&z, up) {
+        Some(x) => Ok(set_view(out, eye, &x, &z)),
+        None => Err(LookAtError::UpParallel),
+    }
+}
+
+#[inline]
+pub fn try_look_at<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], target: &'b [T; 3], up: &'b [T; 3]) -> Result<&'a mut [T; 16], LookAtError> {
+    try_look_to(out, eye, &[target[0] - eye[0], target[1] - eye[1], target[2] - eye[2]], up)
+}
+#[test]
+fn test_try_look_at() {
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    let eye = [3f64, 4f64, 5f64];
+    let up = [0f64, 1f64, 0f64];
+    assert!(try_look_at(&mut a, &eye, &[0f64, 0f64, 0f64], &up).is_ok());
+    look_at(&mut b, &eye, &[0f64, 0f64, 0f64], &up);
+    assert_close(&a, &b, 0f64);
+
+    assert_eq!(try_look_at(&mut a, &eye, &eye, &up).err(), Some(LookAtError::EyeAtTarget));
+    assert_eq!(try_look_at(&mut a, &eye, &[3f64, -4f64, 5f64], &up).err(), Some(LookAtError::UpParallel));
+    assert_eq!(try_look_to(&mut a, &eye, &[0f64, 0f64, -1f64], &[0f64, 0f64, 0f64]).err(), Some(LookAtError::UpParallel));
+}
+#[test]
+fn test_look_to() {
+    let mut rng = Rng(37);
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    for _ in 0..20 {
+        let e = rng.quat();
+        let d = rng.quat();
+        let u = rng.quat();
+        let eye = [e[0], e[1], e[2]];
+        look_to(&mut a, &eye, &[d[0], d[1], d[2]], &[u[0], u[1], u[2]]);
+        look_at(&mut b, &eye, &[e[0] + d[0], e[1] + d[1], e[2] + d[2]], &[u[0], u[1], u[2]]);
+        assert_close(&a, &b, 1e-12f64);
+    }
+}
+
+#[inline]
+pub fn look_to_rh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], direction: &'b [T; 3], up: &'b [T; 3]) -> &'a mut [T; 16] {
+    look_to(out, eye, direction, up)
+}
+
+/// Left-handed `look_to`: the camera looks down its +Z axis.
+#[inline]
+pub fn look_to_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], eye: &'b [T; 3], direction: &'b [T; 3], up: &'b [T; 3]) -> &'a mut [T; 16] {
+    look_to(out, eye, &[-direction[0], -direction[1], -direction[2]], up)
+}
+
 #[inline]
 pub fn compose<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], position: &'b [T; 3], scale: &'b [T; 3], rotation: &'b [T; 4]) -> &'a mut [T; 16] {
     let x = rotation[0];
@@ -153,6 +391,165 @@ pub fn test_compose() {
     ]);
 }
 
+/// Quaternion `[x, y, z, w]` of the rotation in the upper 3x3 of `m` using
+/// Shepperd's method, which takes the square root of the largest of the trace
+/// and the diagonal so it stays accurate for any angle. The upper 3x3 must be
+/// a rotation; `normalize` only removes accumulated drift.
+#[inline]
+pub fn to_quat<'a, 'b, T: Copy + Num>(out: &'a mut [T; 4], m: &'b [T; 16], normalize: bool) -> &'a mut [T; 4] {
+    let m11 = m[0];
+    let m12 = m[4];
+    let m13 = m[8];
+    let m21 = m[1];
+    let m22 = m[5];
+    let m23 = m[9];
+    let m31 = m[2];
+    let m32 = m[6];
+    let m33 = m[10];
+    let trace = m11 + m22 + m33;
+    let quarter = T::from_f32(0.25f32);
+
+    if trace >= m11 && trace >= m22 && trace >= m33 {
+        let s = T::from_isize(2isize) * (T::one() + trace).sqrt();
+        let inv_s = if s != T::zero() {T::one() / s} else {s};
+
+        out[0] = (m32 - m23) * inv_s;
+        out[1] = (m13 - m31) * inv_s;
+        out[2] = (m21 - m12) * inv_s;
+        out[3] = quarter * s;
+    } else if m11 >= m22 && m11 >= m33 {
+        let s = T::from_isize(2isize) * (T::one() + m11 - m22 - m33).sqrt();
+        let inv_s = if s != T::zero() {T::one() / s} else {s};
+
+        out[0] = quarter * s;
+        out[1] = (m12 + m21) * inv_s;
+        out[2] = (m13 + m31) * inv_s;
+        out[3] = (m32 - m23) * inv_s;
+    } else if m22 >= m33 {
+        let s = T::from_isize(2isize) * (T::one() + m22 - m11 - m33).sqrt();
+        let inv_s = if s != T::zero() {T::one() / s} else {s};
+
+        out[0] = (m12 + m21) * inv_s;
+        out[1] = quarter * s;
+        out[2] = (m23 + m32) * inv_s;
+        out[3] = (m13 - m31) * inv_s;
+    } else {
+        let s = T::from_isize(2isize) * (T::one() + m33 - m11 - m22).sqrt();
+        let inv_s = if s != T::zero() {T::one() / s} else {s};
+
+        out[0] = (m13 + m31) * inv_s;
+        out[1] = (m23 + m32) * inv_s;
+        out[2] = quarter * s;
+        out[3] = (m21 - m12) * inv_s;
+    }
+
+    if normalize {
+        let len = (out[0] * out[0] + out[1] * out[1] + out[2] * out[2] + out[3] * out[3]).sqrt();
+        if len != T::zero() {
+            let inv_len = T::one() / len;
+            out[0] = out[0] * inv_len;
+            out[1] = out[1] * inv_len;
+            out[2] = out[2] * inv_len;
+            out[3] = out[3] * inv_len;
+        }
+    }
+    out
+}
+#[test]
+fn test_to_quat() {
+    let mut rng = Rng(61);
+    let mut m = [0f64; 16];
+    let mut n = [0f64; 16];
+    let mut q = [0f64; 4];
+    for _ in 0..50 {
+        from_quat(&mut m, &rng.quat());
+        to_quat(&mut q, &m, false);
+        from_quat(&mut n, &q);
+        assert_close(&m, &n, 1e-12f64);
+    }
+
+    // half turns, where the trace branch loses all precision
+    for axis in [[1f64, 0f64, 0f64], [0f64, 1f64, 0f64], [0f64, 0f64, 1f64], [1f64, 1f64, 0f64]].iter() {
+        from_axis_angle(&mut m, axis, ::core::f64::consts::PI);
+        to_quat(&mut q, &m, false);
+        from_quat(&mut n, &q);
+        assert_close(&m, &n, 1e-12f64);
+    }
+    to_quat(&mut q, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, -1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], false);
+    assert_eq!(q, [0f64, 1f64, 0f64, 0f64]);
+
+    // decompose goes through to_quat once scale is removed
+    let mut position = [0f64; 3];
+    let mut scale = [0f64; 3];
+    let mut rotation = [0f64; 4];
+    for _ in 0..10 {
+        let r = rng.quat();
+        compose(&mut m, &[1f64, 2f64, 3f64], &[2f64, 3f64, 4f64], &r);
+        decompose(&m, &mut position, &mut scale, &mut rotation);
+        from_quat(&mut n, &r);
+        to_quat(&mut q, &n, false);
+        assert_close(&rotation, &q, 1e-12f64);
+    }
+
+    from_quat(&mut m, &rng.quat());
+    for v in m[..11].iter_mut() {
+        *v = *v * 1.001f64;
+    }
+    to_quat(&mut q, &m, true);
+    assert!(((q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3]) - 1f64).abs() < 1e-12f64);
+}
+
+/// Axis and angle of the rotation in the upper 3x3 of `m`. The angle is in
+/// `[0, pi]`; with no rotation the axis is `[1, 0, 0]`.
+#[inline]
+pub fn to_axis_angle<'b, T: Copy + Num>(m: &'b [T; 16], axis: &mut [T; 3]) -> T {
+    let mut q = [T::zero(); 4];
+    to_quat(&mut q, m, true);
+    if q[3] < T::zero() {
+        q = [T::zero() - q[0], T::zero() - q[1], T::zero() - q[2], T::zero() - q[3]];
+    }
+
+    let sin_half = vec3::length_values(q[0], q[1], q[2]);
+    if sin_half == T::zero() {
+        axis[0] = T::one();
+        axis[1] = T::zero();
+        axis[2] = T::zero();
+        T::zero()
+    } else {
+        axis[0] = q[0] / sin_half;
+        axis[1] = q[1] / sin_half;
+        axis[2] = q[2] / sin_half;
+        T::from_isize(2isize) * sin_half.atan2(q[3])
+    }
+}
+#[test]
+fn test_to_axis_angle() {
+    let mut rng = Rng(67);
+    let mut m = [0f64; 16];
+    let mut n = [0f64; 16];
+    let mut axis = [0f64; 3];
+    for _ in 0..50 {
+        from_quat(&mut m, &rng.quat());
+        let angle = to_axis_angle(&m, &mut axis);
+        assert!(angle >= 0f64 && angle <= ::core::f64::consts::PI);
+        from_axis_angle(&mut n, &axis, angle);
+        assert_close(&m, &n, 1e-12f64);
+    }
+
+    from_axis_angle(&mut m, &[0f64, 0f64, -2f64], 0.75f64);
+    let angle = to_axis_angle(&m, &mut axis);
+    assert!((angle - 0.75f64).abs() < 1e-12f64);
+    assert_close(&axis, &[0f64, 0f64, -1f64], 1e-12f64);
+
+    assert_eq!(to_axis_angle(&new_identity::<f64>(), &mut axis), 0f64);
+    assert_eq!(axis, [1f64, 0f64, 0f64]);
+}
+
 #[inline]
 pub fn decompose<'a, 'b, T: Copy + Num>(out: &'b [T; 16], position: &mut [T; 3], scale: &mut [T; 3], rotation: &mut [T; 4]) {
     let mut m11 = out[0];
@@ -191,41 +588,12 @@ pub fn decompose<'a, 'b, T: Copy + Num>(out: &'b [T; 16], position: &mut [T; 3],
     m32 = m32 * inv_sy;
     m33 = m33 * inv_sz;
 
-    let trace = m11 + m22 + m33;
-
-    if trace > T::zero() {
-        let s = T::from_f32(0.5f32) / (trace + T::one()).sqrt();
-        let inv_s = if s != T::zero() {T::one() / s} else {s};
-
-        rotation[0] = (m32 - m23) * s;
-        rotation[1] = (m13 - m31) * s;
-        rotation[2] = (m21 - m12) * s;
-        rotation[3] = T::from_f32(0.25f32) * inv_s;
-    } else if m11 > m22 && m11 > m33 {
-        let s = T::from_isize(2isize) * (T::one() + m11 - m22 - m33).sqrt();
-        let inv_s = if s != T::zero() {T::one() / s} else {s};
-
-        rotation[0] = T::from_f32(0.25f32) * s;
-        rotation[1] = (m12 + m21) * inv_s;
-        rotation[2] = (m13 + m31) * inv_s;
-        rotation[3] = (m32 - m23) * inv_s;
-    } else if m22 > m33 {
-        let s = T::from_isize(2isize) * (T::one() + m22 - m11 - m33).sqrt();
-        let inv_s = if s != T::zero() {T::one() / s} else {s};
-
-        rotation[0] = (m12 + m21) * inv_s;
-        rotation[1] = T::from_f32(0.25f32) * s;
-        rotation[2] = (m23 + m32) * inv_s;
-        rotation[3] = (m13 - m31) * inv_s;
-    } else {
-        let s = T::from_isize(2isize) * (T::one() + m33 - m11 - m22).sqrt();
-        let inv_s = if s != T::zero() {T::one() / s} else {s};
-
-        rotation[0] = (m13 + m31) * inv_s;
-        rotation[1] = (m23 + m32) * inv_s;
-        rotation[2] = T::from_f32(0.25f32) * s;
-        rotation[3] = (m21 - m12) * inv_s;
-    }
+    to_quat(rotation, &[
+        m11, m21, m31, T::zero(),
+        m12, m22, m32, T::zero(),
+        m13, m23, m33, T::zero(),
+        T::zero(), T::zero(), T::zero(), T::one()
+    ], false);
 }
 #[test]
 pub fn test_decompose() {
@@ -243,6 +611,233 @@ pub fn test_decompose() {
     assert_eq!(rotation, [0f32, 0f32, 0f32, 1f32]);
 }
 
+/// Decomposes `m` as `P * T * R * H * S` (the "unmatrix" algorithm): perspective
+/// `P` with bottom row `perspective`, translation, rotation quaternion, shear
+/// `H` (xy, xz, yz) and scale, where a mirrored matrix gets negative scales.
+/// Returns `false` and leaves the outputs untouched if the upper 3x3 of `m`
+/// is singular.
+#[inline]
+pub fn decompose_full<'b, T: Copy + Num>(
+    m: &'b [T; 16],
+    translation: &mut [T; 3],
+    scale: &mut [T; 3],
+    shear: &mut [T; 3],
+    rotation: &mut [T; 4],
+    perspective: &mut [T; 4]
+) -> bool {
+    let mut x0 = m[0];
+    let mut x1 = m[1];
+    let mut x2 = m[2];
+    let mut y0 = m[4];
+    let mut y1 = m[5];
+    let mut y2 = m[6];
+    let mut z0 = m[8];
+    let mut z1 = m[9];
+    let mut z2 = m[10];
+
+    let c00 = y1 * z2 - y2 * z1;
+    let c01 = y2 * z0 - y0 * z2;
+    let c02 = y0 * z1 - y1 * z0;
+    let d = x0 * c00 + x1 * c01 + x2 * c02;
+
+    if d == T::zero() {
+        return false;
+    }
+    let inv_d = T::one() / d;
+
+    // the rows of the inverse of the upper 3x3 are the cross products of its columns
+    let px = m[3];
+    let py = m[7];
+    let pz = m[11];
+    let qx = (c00 * px + (z1 * x2 - z2 * x1) * py + (x1 * y2 - x2 * y1) * pz) * inv_d;
+    let qy = (c01 * px + (z2 * x0 - z0 * x2) * py + (x2 * y0 - x0 * y2) * pz) * inv_d;
+    let qz = (c02 * px + (z0 * x1 - z1 * x0) * py + (x0 * y1 - x1 * y0) * pz) * inv_d;
+
+    let mut sx = vec3::length_values(x0, x1, x2);
+    let mut inv_s = T::one() / sx;
+    x0 = x0 * inv_s;
+    x1 = x1 * inv_s;
+    x2 = x2 * inv_s;
+
+    let mut xy = x0 * y0 + x1 * y1 + x2 * y2;
+    y0 = y0 - x0 * xy;
+    y1 = y1 - x1 * xy;
+    y2 = y2 - x2 * xy;
+
+    let mut sy = vec3::length_values(y0, y1, y2);
+    inv_s = T::one() / sy;
+    y0 = y0 * inv_s;
+    y1 = y1 * inv_s;
+    y2 = y2 * inv_s;
+    xy = xy * inv_s;
+
+    let mut xz = x0 * z0 + x1 * z1 + x2 * z2;
+    z0 = z0 - x0 * xz;
+    z1 = z1 - x1 * xz;
+    z2 = z2 - x2 * xz;
+
+    let mut yz = y0 * z0 + y1 * z1 + y2 * z2;
+    z0 = z0 - y0 * yz;
+    z1 = z1 - y1 * yz;
+    z2 = z2 - y2 * yz;
+
+    let mut sz = vec3::length_values(z0, z1, z2);
+    inv_s = T::one() / sz;
+    z0 = z0 * inv_s;
+    z1 = z1 * inv_s;
+    z2 = z2 * inv_s;
+    xz = xz * inv_s;
+    yz = yz * inv_s;
+
+    if d < T::zero() {
+        sx = T::zero() - sx;
+        sy = T::zero() - sy;
+        sz = T::zero() - sz;
+        x0 = T::zero() - x0;
+        x1 = T::zero() - x1;
+        x2 = T::zero() - x2;
+        y0 = T::zero() - y0;
+        y1 = T::zero() - y1;
+        y2 = T::zero() - y2;
+        z0 = T::zero() - z0;
+        z1 = T::zero() - z1;
+        z2 = T::zero() - z2;
+    }
+
+    to_quat(rotation, &[
+        x0, x1, x2, T::zero(),
+        y0, y1, y2, T::zero(),
+        z0, z1, z2, T::zero(),
+        T::zero(), T::zero(), T::zero(), T::one()
+    ], false);
+
+    translation[0] = m[12];
+    translation[1] = m[13];
+    translation[2] = m[14];
+
+    scale[0] = sx;
+    scale[1] = sy;
+    scale[2] = sz;
+
+    shear[0] = xy;
+    shear[1] = xz;
+    shear[2] = yz;
+
+    perspective[0] = qx;
+    perspective[1] = qy;
+    perspective[2] = qz;
+    perspective[3] = m[15] - (qx * m[12] + qy * m[13] + qz * m[14]);
+
+    true
+}
+
+/// Inverse of `decompose_full`.
+#[inline]
+pub fn recompose<'a, 'b, T: Copy + Num>(
+    out: &'a mut [T; 16],
+    translation: &'b [T; 3],
+    scale: &'b [T; 3],
+    shear: &'b [T; 3],
+    rotation: &'b [T; 4],
+    perspective: &'b [T; 4]
+) -> &'a mut [T; 16] {
+    let mut r = [T::zero(); 16];
+    from_quat(&mut r, rotation);
+
+    let sx = scale[0];
+    let sy = scale[1];
+    let sz = scale[2];
+    let xy = shear[0];
+    let xz = shear[1];
+    let yz = shear[2];
+    let qx = perspective[0];
+    let qy = perspective[1];
+    let qz = perspective[2];
+    let tx = translation[0];
+    let ty = translation[1];
+    let tz = translation[2];
+
+    out[0] = r[0] * sx;
+    out[1] = r[1] * sx;
+    out[2] = r[2] * sx;
+    out[4] = (r[0] * xy + r[4]) * sy;
+    out[5] = (r[1] * xy + r[5]) * sy;
+    out[6] = (r[2] * xy + r[6]) * sy;
+    out[8] = (r[0] * xz + r[4] * yz + r[8]) * sz;
+    out[9] = (r[1] * xz + r[5] * yz + r[9]) * sz;
+    out[10] = (r[2] * xz + r[6] * yz + r[10]) * sz;
+
+    out[3] = qx * out[0] + qy * out[1] + qz * out[2];
+    out[7] = qx * out[4] + qy * out[5] + qz * out[6];
+    out[11] = qx * out[8] + qy * out[9] + qz * out[10];
+
+    out[12] = tx;
+    out[13] = ty;
+    out[14] = tz;
+    out[15] = qx * tx + qy * ty + qz * tz + perspective[3];
+    out
+}
+#[test]
+fn test_decompose_full() {
+    let mut translation = [0f64; 3];
+    let mut scale = [0f64; 3];
+    let mut shear = [0f64; 3];
+    let mut rotation = [0f64; 4];
+    let mut perspective = [0f64; 4];
+    let mut m = [0f64; 16];
+
+    let mut rng = Rng(3);
+    for _ in 0..100 {
+        let a = rng.mat4();
+        assert!(decompose_full(&a, &mut translation, &mut scale, &mut shear, &mut rotation, &mut perspective));
+        recompose(&mut m, &translation, &scale, &shear, &rotation, &perspective);
+        assert_close(&a, &m, 1e-9f64);
+    }
+
+    let q = rng.quat();
+    compose(&mut m, &[1f64, 2f64, 3f64], &[2f64, 3f64, -4f64], &q);
+    assert!(decompose_full(&m, &mut translation, &mut scale, &mut shear, &mut rotation, &mut perspective));
+    assert_close(&scale, &[-2f64, -3f64, -4f64], 1e-9f64);
+
+    compose(&mut m, &[1f64, 2f64, 3f64], &[2f64, 3f64, 4f64], &q);
+    assert!(decompose_full(&m, &mut translation, &mut scale, &mut shear, &mut rotation, &mut perspective));
+    assert_close(&translation, &[1f64, 2f64, 3f64], 1e-9f64);
+    assert_close(&scale, &[2f64, 3f64, 4f64], 1e-9f64);
+    assert_close(&shear, &[0f64, 0f64, 0f64], 1e-9f64);
+    assert_close(&perspective, &[0f64, 0f64, 0f64, 1f64], 1e-9f64);
+    let sign = if rotation[3] * q[3] < 0f64 {-1f64} else {1f64};
+    assert_close(&rotation, &[q[0] * sign, q[1] * sign, q[2] * sign, q[3] * sign], 1e-9f64);
+
+    m[8] = 0f64;
+    m[9] = 0f64;
+    m[10] = 0f64;
+    assert!(!decompose_full(&m, &mut translation, &mut scale, &mut shear, &mut rotation, &mut perspective));
+
+    // scale (2, 3, 4) with half of x added to y, then moved to (5, 6, 7):
+    // the shear must come out of the y column before its length is taken
+    assert!(decompose_full(&[
+        2f64, 0f64, 0f64, 0f64,
+        1.5f64, 3f64, 0f64, 0f64,
+        0f64, 0f64, 4f64, 0f64,
+        5f64, 6f64, 7f64, 1f64
+    ], &mut translation, &mut scale, &mut shear, &mut rotation, &mut perspective));
+    assert_close(&translation, &[5f64, 6f64, 7f64], 1e-15f64);
+    assert_close(&scale, &[2f64, 3f64, 4f64], 1e-15f64);
+    assert_close(&shear, &[0.5f64, 0f64, 0f64], 1e-15f64);
+    assert_close(&rotation, &[0f64, 0f64, 0f64, 1f64], 1e-15f64);
+    assert_close(&perspective, &[0f64, 0f64, 0f64, 1f64], 0f64);
+
+    // a bottom row of (0, 0, -1, 0) is the w = -z of a projection
+    assert!(decompose_full(&[
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, -1f64,
+        0f64, 0f64, 0f64, 0f64
+    ], &mut translation, &mut scale, &mut shear, &mut rotation, &mut perspective));
+    assert_close(&scale, &[1f64, 1f64, 1f64], 0f64);
+    assert_close(&perspective, &[0f64, 0f64, -1f64, 0f64], 0f64);
+}
+
 #[inline]
 pub fn set_position<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], v: &'b [T; 3]) -> &'a mut [T; 16] {
     out[12] = v[0];
@@ -259,24 +854,342 @@ pub fn extract_position<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T;
     out
 }
 
+#[inline]
+fn cross<T: Copy + Num>(a: &[T; 3], b: &[T; 3]) -> [T; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+#[inline]
+fn normalized<T: Copy + Num>(v: &[T; 3]) -> Option<[T; 3]> {
+    let len = vec3::length_values(v[0], v[1], v[2]);
+    if len == T::zero() {
+        None
+    } else {
+        Some([v[0] / len, v[1] / len, v[2] / len])
+    }
+}
+
+// a unit vector perpendicular to `v`, built from the world axis least aligned
+// with it
+#[inline]
+fn perpendicular<T: Copy + Num>(v: &[T; 3]) -> [T; 3] {
+    let xx = v[0] * v[0];
+    let yy = v[1] * v[1];
+    let zz = v[2] * v[2];
+    let p = if xx <= yy && xx <= zz {
+        [T::zero(), v[2], T::zero() - v[1]]
+    } else if yy <= zz {
+        [T::zero() - v[2], T::zero(), v[0]]
+    } else {
+        [v[1], T::zero() - v[0], T::zero()]
+    };
+    match normalized(&p) {
+        Some(p) => p,
+        None => [T::one(), T::zero(), T::zero()],
+    }
+}
+
+/// Per-axis scale of the upper 3x3 of `a`: the lengths of its columns, all
+/// negated when the determinant is negative, matching `decompose_full`.
+#[inline]
+pub fn extract_scale<'a, 'b, T: Copy + Num>(out: &'a mut [T; 3], a: &'b [T; 16]) -> &'a mut [T; 3] {
+    let x = [a[0], a[1], a[2]];
+    let y = [a[4], a[5], a[6]];
+    let z = [a[8], a[9], a[10]];
+    let yz = cross(&y, &z);
+    let d = x[0] * yz[0] + x[1] * yz[1] + x[2] * yz[2];
+
+    out[0] = vec3::length_values(x[0], x[1], x[2]);
+    out[1] = vec3::length_values(y[0], y[1], y[2]);
+    out[2] = vec3::length_values(z[0], z[1], z[2]);
+    if d < T::zero() {
+        out[0] = T::zero() - out[0];
+        out[1] = T::zero() - out[1];
+        out[2] = T::zero() - out[2];
+    }
+    out
+}
+#[test]
+fn test_extract_scale() {
+    let mut m = [0f64; 16];
+    let mut scale = [0f64; 3];
+    compose(&mut m, &[1f64, 2f64, 3f64], &[2f64, 3f64, 4f64], &[0f64, 0.6f64, 0f64, 0.8f64]);
+    assert_close(extract_scale(&mut scale, &m), &[2f64, 3f64, 4f64], 1e-12f64);
+    compose(&mut m, &[1f64, 2f64, 3f64], &[2f64, -3f64, 4f64], &[0f64, 0.6f64, 0f64, 0.8f64]);
+    assert_close(extract_scale(&mut scale, &m), &[-2f64, -3f64, -4f64], 1e-12f64);
+}
+
+/// The rotation part of `a` with scale divided out and no translation. The
+/// axes are re-orthogonalized, x first, so the result is always a proper
+/// rotation: a negative determinant flips every axis (see `extract_scale`)
+/// and an axis with zero scale is rebuilt from the others.
 #[inline]
 pub fn extract_rotation<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
-    let sx = vec3::length_values(a[0], a[1], a[2]);
-    let sy = vec3::length_values(a[4], a[5], a[6]);
-    let sz = vec3::length_values(a[8], a[9], a[10]);
-
-    out[0] = a[0] * sx;
-    out[1] = a[1] * sx;
-    out[2] = a[2] * sx;
-    out[4] = a[4] * sy;
-    out[5] = a[5] * sy;
-    out[6] = a[6] * sy;
-    out[8] = a[8] * sz;
-    out[9] = a[9] * sz;
-    out[10] = a[10] * sz;
+    let mut scale = [T::zero(); 3];
+    extract_scale(&mut scale, a);
+    let axis = |i: usize| if scale[i] == T::zero() {
+        None
+    } else {
+        Some([a[i * 4] / scale[i], a[i * 4 + 1] / scale[i], a[i * 4 + 2] / scale[i]])
+    };
+
+    let (x, y) = match (axis(0), axis(1), axis(2)) {
+        (Some(x), Some(y), _) => (x, y),
+        (Some(x), None, Some(z)) => (x, cross(&z, &x)),
+        (None, Some(y), Some(z)) => (match normalized(&cross(&y, &z)) {
+            Some(x) => x,
+            None => perpendicular(&y),
+        }, y),
+        (Some(x), None, None) => (x, perpendicular(&x)),
+        (None, Some(y), None) => (perpendicular(&y), y),
+        (None, None, Some(z)) => {
+            let x = perpendicular(&z);
+            (x, cross(&z, &x))
+        },
+        (None, None, None) => ([T::one(), T::zero(), T::zero()], [T::zero(), T::one(), T::zero()]),
+    };
+
+    let xy = x[0] * y[0] + x[1] * y[1] + x[2] * y[2];
+    let y = match normalized(&[y[0] - x[0] * xy, y[1] - x[1] * xy, y[2] - x[2] * xy]) {
+        Some(y) => y,
+        None => perpendicular(&x),
+    };
+    let z = cross(&x, &y);
+
+    from_basis(out, &x, &y, &z)
+}
+#[test]
+fn test_extract_rotation() {
+    let mut rng = Rng(71);
+    let mut m = [0f64; 16];
+    let mut r = [0f64; 16];
+    let mut expected = [0f64; 16];
+    let mut scale = [0f64; 3];
+    for _ in 0..20 {
+        let q = rng.quat();
+        let s = [rng.next() * 2f64 + 2.5f64, rng.next() * 2f64 - 2.5f64, rng.next() * 2f64 + 2.5f64];
+        compose(&mut m, &[rng.next(), rng.next(), rng.next()], &s, &q);
+        extract_rotation(&mut r, &m);
+        extract_scale(&mut scale, &m);
+        assert!(::misc::is_rigid(&r, 1e-12f64));
+        assert!(::misc::determinant(&r) > 0f64);
+        assert_close(&r[12..], &[0f64, 0f64, 0f64, 1f64], 0f64);
+        ::transform::scale(&mut expected, &r, &scale);
+        assert_close(&expected[..12], &m[..12], 1e-12f64);
+    }
+
+    // a flattened axis is rebuilt from the other two
+    compose(&mut m, &[0f64, 0f64, 0f64], &[2f64, 0f64, 3f64], &[0f64, 0.6f64, 0f64, 0.8f64]);
+    extract_rotation(&mut r, &m);
+    from_quat(&mut expected, &[0f64, 0.6f64, 0f64, 0.8f64]);
+    assert_close(&r, &expected, 1e-12f64);
+
+    extract_rotation(&mut r, &[0f64; 16]);
+    assert_close(&r, &new_identity::<f64>(), 0f64);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+#[inline]
+fn dot<T: Copy + Num>(a: &[T; 3], b: &[T; 3]) -> T {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline]
+fn set_upper3<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], c: &[[T; 3]; 3]) -> &'a mut [T; 16] {
+    for i in 0..3 {
+        out[i * 4] = c[i][0];
+        out[i * 4 + 1] = c[i][1];
+        out[i * 4 + 2] = c[i][2];
+        out[i * 4 + 3] = a[i * 4 + 3];
+    }
+    out[12] = a[12];
+    out[13] = a[13];
+    out[14] = a[14];
+    out[15] = a[15];
     out
 }
 
+/// Gram–Schmidt orthonormalization of the upper 3x3 of `a`. The `primary`
+/// axis keeps its direction, the next axis in x, y, z order is made
+/// orthogonal to it and the last is their cross product, so the result is a
+/// proper rotation. Translation and the bottom row are copied from `a`.
+#[inline]
+pub fn orthonormalize<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], primary: Axis) -> &'a mut [T; 16] {
+    let p = match primary {
+        Axis::X => 0,
+        Axis::Y => 1,
+        Axis::Z => 2,
+    };
+    let q = (p + 1) % 3;
+    let r = (p + 2) % 3;
+
+    let u = match normalized(&[a[p * 4], a[p * 4 + 1], a[p * 4 + 2]]) {
+        Some(u) => u,
+        None => {
+            let mut axis = [T::zero(); 3];
+            axis[p] = T::one();
+            axis
+        },
+    };
+    let reject = |v: &[T; 3]| {
+        let uv = dot(&u, v);
+        normalized(&[v[0] - u[0] * uv, v[1] - u[1] * uv, v[2] - u[2] * uv])
+    };
+    let v = match reject(&[a[q * 4], a[q * 4 + 1], a[q * 4 + 2]]) {
+        Some(v) => v,
+        None => {
+            let mut axis = [T::zero(); 3];
+            axis[q] = T::one();
+            match reject(&axis) {
+                Some(v) => v,
+                None => perpendicular(&u),
+            }
+        },
+    };
+
+    let mut c = [[T::zero(); 3]; 3];
+    c[p] = u;
+    c[q] = v;
+    c[r] = cross(&u, &v);
+    set_upper3(out, a, &c)
+}
+#[test]
+fn test_orthonormalize() {
+    let mut rng = Rng(73);
+    let mut r = [0f64; 16];
+    for _ in 0..10 {
+        let mut m = rng.trs(true);
+        for _ in 0..1000 {
+            let tmp = m;
+            rotate_x(&mut m, &tmp, 0.1f64);
+            m[1] += 1e-5f64 * rng.next();
+            m[6] -= 1e-5f64 * rng.next();
+        }
+        assert!(!::misc::is_orthonormal(&m, 1e-6f64));
+
+        for &(axis, i) in [(Axis::X, 0), (Axis::Y, 4), (Axis::Z, 8)].iter() {
+            orthonormalize(&mut r, &m, axis);
+            assert!(::misc::is_rigid(&r, 1e-12f64));
+            assert!(::misc::determinant(&r) > 0f64);
+            assert_close(&r[12..], &m[12..], 0f64);
+            let len = vec3::length_values(m[i], m[i + 1], m[i + 2]);
+            assert_close(&r[i..i + 3], &[m[i] / len, m[i + 1] / len, m[i + 2] / len], 1e-12f64);
+        }
+    }
+
+    orthonormalize(&mut r, &[0f64; 16], Axis::Y);
+    assert_close(&r[..12], &new_identity::<f64>()[..12], 0f64);
+
+    // y leaning towards x: keeping x straightens y, keeping y tilts x
+    let h = 0.5f64.sqrt();
+    let skewed = [
+        1f64, 0f64, 0f64, 0f64,
+        1f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ];
+    orthonormalize(&mut r, &skewed, Axis::X);
+    assert_close(&r, &new_identity::<f64>(), 1e-15f64);
+    orthonormalize(&mut r, &skewed, Axis::Y);
+    assert_close(&r, &[
+        h, -h, 0f64, 0f64,
+        h, h, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+}
+
+/// The rotation closest to the upper 3x3 of `a` in the Frobenius norm, `U V^T`
+/// from its singular value decomposition. When `a` is mirrored the axis of
+/// least stretch is flipped back, and when its rank is below two, so that
+/// the closest rotation is not unique, it falls back to `orthonormalize`
+/// about x. Translation and the bottom row are copied from `a`.
+#[inline]
+pub fn nearest_rotation<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16]) -> &'a mut [T; 16] {
+    let mut u = [T::zero(); 16];
+    let mut v = [T::zero(); 16];
+    let mut sigma = [T::zero(); 3];
+    svd3(&mut u, &mut sigma, &mut v, a);
+    if sigma[1] == T::zero() {
+        return orthonormalize(out, a, Axis::X);
+    }
+
+    // R = U diag(1, 1, d) V^T, where d = -1 flips the smallest singular
+    // direction when U V^T would be a reflection
+    let column = |m: &[T; 16], j: usize| [m[j * 4], m[j * 4 + 1], m[j * 4 + 2]];
+    let det = |m: &[T; 16]| dot(&column(m, 0), &cross(&column(m, 1), &column(m, 2)));
+    let d = if det(&u) * det(&v) < T::zero() {T::zero() - T::one()} else {T::one()};
+    let mut r = [[T::zero(); 3]; 3];
+    for (j, c) in r.iter_mut().enumerate() {
+        for (i, x) in c.iter_mut().enumerate() {
+            *x = u[i] * v[j] + u[4 + i] * v[4 + j] + d * u[8 + i] * v[8 + j];
+        }
+    }
+    set_upper3(out, a, &r)
+}
+#[test]
+fn test_nearest_rotation() {
+    let mut rng = Rng(79);
+    let mut m = [0f64; 16];
+    let mut r = [0f64; 16];
+    let mut expected = [0f64; 16];
+    for _ in 0..20 {
+        let q = rng.quat();
+        let p = [rng.next(), rng.next(), rng.next()];
+        let s = [rng.next() + 2f64, rng.next() + 2f64, rng.next() + 2f64];
+        compose(&mut m, &p, &s, &q);
+        compose(&mut expected, &p, &[1f64, 1f64, 1f64], &q);
+        nearest_rotation(&mut r, &m);
+        assert_close(&r, &expected, 1e-12f64);
+
+        // mirrored: flip the least stretched axis back
+        compose(&mut m, &p, &[1f64, 1f64, -0.1f64], &q);
+        nearest_rotation(&mut r, &m);
+        assert_close(&r, &expected, 1e-12f64);
+    }
+
+    // for a general matrix R^T M is symmetric
+    for _ in 0..20 {
+        let m = rng.mat4();
+        nearest_rotation(&mut r, &m);
+        assert!(::misc::is_orthonormal(&r, 1e-12f64));
+        assert!(::misc::determinant(&[
+            r[0], r[1], r[2], 0f64, r[4], r[5], r[6], 0f64, r[8], r[9], r[10], 0f64, 0f64, 0f64, 0f64, 1f64
+        ]) > 0f64);
+        if ::misc::determinant(&[
+            m[0], m[1], m[2], 0f64, m[4], m[5], m[6], 0f64, m[8], m[9], m[10], 0f64, 0f64, 0f64, 0f64, 1f64
+        ]) > 0f64 {
+            let h = |i: usize, j: usize| r[i * 4] * m[j * 4] + r[i * 4 + 1] * m[j * 4 + 1] + r[i * 4 + 2] * m[j * 4 + 2];
+            assert_close(&[h(0, 1), h(0, 2), h(1, 2)], &[h(1, 0), h(2, 0), h(2, 1)], 1e-12f64);
+        }
+        assert_close(&r[12..], &m[12..], 0f64);
+    }
+
+    // the proper rotation closest to diag(2, 3, -4) flips the least
+    // stretched axis as well
+    nearest_rotation(&mut r, &[
+        2f64, 0f64, 0f64, 0f64,
+        0f64, 3f64, 0f64, 0f64,
+        0f64, 0f64, -4f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]);
+    assert_close(&r, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, -1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-12f64);
+
+    nearest_rotation(&mut r, &[0f64; 16]);
+    assert_close(&r[..12], &new_identity::<f64>()[..12], 0f64);
+}
+
 #[inline]
 pub fn translate<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], v: &'b [T; 3]) -> &'a mut [T; 16] {
     let x = v[0];
@@ -488,6 +1401,223 @@ pub fn from_quat<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], q: &'b [T; 4]) ->
     out
 }
 
+/// Rotation of `angle` radians about `axis`, which need not be normalized. A
+/// zero axis gives identity.
+#[inline]
+pub fn from_axis_angle<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], axis: &'b [T; 3], angle: T) -> &'a mut [T; 16] {
+    let len = vec3::length_values(axis[0], axis[1], axis[2]);
+
+    if len == T::zero() {
+        return identity(out);
+    }
+
+    let x = axis[0] / len;
+    let y = axis[1] / len;
+    let z = axis[2] / len;
+    let s = angle.sin();
+    let c = angle.cos();
+    let t = T::one() - c;
+
+    out[0] = x * x * t + c;
+    out[1] = y * x * t + z * s;
+    out[2] = z * x * t - y * s;
+    out[3] = T::zero();
+    out[4] = x * y * t - z * s;
+    out[5] = y * y * t + c;
+    out[6] = z * y * t + x * s;
+    out[7] = T::zero();
+    out[8] = x * z * t + y * s;
+    out[9] = y * z * t - x * s;
+    out[10] = z * z * t + c;
+    out[11] = T::zero();
+    out[12] = T::zero();
+    out[13] = T::zero();
+    out[14] = T::zero();
+    out[15] = T::one();
+    out
+}
+#[test]
+fn test_from_axis_angle() {
+    let identity = new_identity::<f64>();
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    from_axis_angle(&mut a, &[2f64, 0f64, 0f64], 0.5f64);
+    rotate_x(&mut b, &identity, 0.5f64);
+    assert_close(&a, &b, 1e-12f64);
+    from_axis_angle(&mut a, &[0f64, -1f64, 0f64], 0.5f64);
+    rotate_y(&mut b, &identity, -0.5f64);
+    assert_close(&a, &b, 1e-12f64);
+
+    let mut rng = Rng(43);
+    for _ in 0..20 {
+        let q = rng.quat();
+        let angle = 2f64 * q[3].acos();
+        from_axis_angle(&mut a, &[q[0], q[1], q[2]], angle);
+        from_quat(&mut b, &q);
+        assert_close(&a, &b, 1e-12f64);
+    }
+
+    from_axis_angle(&mut a, &[0f64, 0f64, 0f64], 0.5f64);
+    assert_close(&a, &identity, 0f64);
+}
+
+/// Post-multiplies `a` by a rotation of `angle` radians about `axis`, like
+/// `rotate_x`.
+#[inline]
+pub fn rotate_axis<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], a: &'b [T; 16], axis: &'b [T; 3], angle: T) -> &'a mut [T; 16] {
+    let mut rotation = new_identity();
+    from_axis_angle(&mut rotation, axis, angle);
+    ::mul::mul(out, a, &rotation)
+}
+#[test]
+fn test_rotate_axis() {
+    let mut rng = Rng(47);
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    for _ in 0..10 {
+        let m = rng.trs(false);
+        rotate_axis(&mut a, &m, &[0f64, 0f64, 3f64], 1.25f64);
+        rotate_z(&mut b, &m, 1.25f64);
+        assert_close(&a, &b, 1e-12f64);
+    }
+
+    // the axis need not be unit length: a third of a turn about the
+    // diagonal cycles x to y to z
+    rotate_axis(&mut a, &new_identity(), &[1f64, 1f64, 1f64], 2f64 * ::core::f64::consts::PI / 3f64);
+    assert_close(&a, &[
+        0f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        1f64, 0f64, 0f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+}
+
+/// The shortest rotation taking the unit vector `from` onto the unit vector
+/// `to`. Antiparallel vectors rotate half a turn about an axis perpendicular
+/// to `from`.
+#[inline]
+pub fn from_rotation_arc<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], from: &'b [T; 3], to: &'b [T; 3]) -> &'a mut [T; 16] {
+    let d = from[0] * to[0] + from[1] * to[1] + from[2] * to[2];
+
+    if d + T::one() <= T::from_isize(16isize) * epsilon::<T>() {
+        from_axis_angle(out, &perpendicular(from), T::from_f64(::core::f64::consts::PI))
+    } else {
+        let x = from[1] * to[2] - from[2] * to[1];
+        let y = from[2] * to[0] - from[0] * to[2];
+        let z = from[0] * to[1] - from[1] * to[0];
+        let w = T::one() + d;
+        let len = (x * x + y * y + z * z + w * w).sqrt();
+        from_quat(out, &[x / len, y / len, z / len, w / len])
+    }
+}
+#[test]
+fn test_from_rotation_arc() {
+    let mut rng = Rng(53);
+    let mut m = [0f64; 16];
+    let mut p = [0f64; 3];
+    for _ in 0..20 {
+        let a = rng.quat();
+        let b = rng.quat();
+        let from = [a[0], a[1], a[2]];
+        let to = [b[0], b[1], b[2]];
+        let la = vec3::length_values(from[0], from[1], from[2]);
+        let lb = vec3::length_values(to[0], to[1], to[2]);
+        let from = [from[0] / la, from[1] / la, from[2] / la];
+        let to = [to[0] / lb, to[1] / lb, to[2] / lb];
+
+        from_rotation_arc(&mut m, &from, &to);
+        ::point::transform_vector3(&mut p, &m, &from);
+        assert_close(&p, &to, 1e-12f64);
+        assert!(::misc::is_rigid(&m, 1e-12f64));
+
+        from_rotation_arc(&mut m, &from, &[-from[0], -from[1], -from[2]]);
+        ::point::transform_vector3(&mut p, &m, &from);
+        assert_close(&p, &[-from[0], -from[1], -from[2]], 1e-12f64);
+        assert!(::misc::is_rigid(&m, 1e-12f64));
+    }
+
+    from_rotation_arc(&mut m, &[0f64, 1f64, 0f64], &[0f64, 1f64, 0f64]);
+    assert_close(&m, &new_identity::<f64>(), 0f64);
+
+    // x onto -x has no cross product to turn about; y and z tie as the
+    // least aligned axis, y wins, and the half turn is about x cross y
+    from_rotation_arc(&mut m, &[1f64, 0f64, 0f64], &[-1f64, 0f64, 0f64]);
+    assert_close(&m, &[
+        -1f64, 0f64, 0f64, 0f64,
+        0f64, -1f64, 0f64, 0f64,
+        0f64, 0f64, 1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ], 1e-15f64);
+}
+
+/// Sets the upper 3x3 of `out` to the columns `x`, `y` and `z`, with no
+/// translation.
+#[inline]
+pub fn from_basis<'a, 'b, T: Copy + Num>(out: &'a mut [T; 16], x: &'b [T; 3], y: &'b [T; 3], z: &'b [T; 3]) -> &'a mut [T; 16] {
+    out[0] = x[0];
+    out[1] = x[1];
+    out[2] = x[2];
+    out[3] = T::zero();
+    out[4] = y[0];
+    out[5] = y[1];
+    out[6] = y[2];
+    out[7] = T::zero();
+    out[8] = z[0];
+    out[9] = z[1];
+    out[10] = z[2];
+    out[11] = T::zero();
+    out[12] = T::zero();
+    out[13] = T::zero();
+    out[14] = T::zero();
+    out[15] = T::one();
+    out
+}
+
+/// Reads the columns of the upper 3x3 of `m` as axis vectors.
+#[inline]
+pub fn to_basis<'b, T: Copy + Num>(m: &'b [T; 16], x: &mut [T; 3], y: &mut [T; 3], z: &mut [T; 3]) {
+    x[0] = m[0];
+    x[1] = m[1];
+    x[2] = m[2];
+    y[0] = m[4];
+    y[1] = m[5];
+    y[2] = m[6];
+    z[0] = m[8];
+    z[1] = m[9];
+    z[2] = m[10];
+}
+#[test]
+fn test_basis() {
+    let mut rng = Rng(59);
+    let mut m = [0f64; 16];
+    let mut x = [0f64; 3];
+    let mut y = [0f64; 3];
+    let mut z = [0f64; 3];
+    let mut p = [0f64; 3];
+    for _ in 0..10 {
+        let q = rng.quat();
+        from_quat(&mut m, &q);
+        to_basis(&m, &mut x, &mut y, &mut z);
+        ::point::transform_vector3(&mut p, &m, &[0f64, 1f64, 0f64]);
+        assert_close(&p, &y, 0f64);
+
+        let mut n = [0f64; 16];
+        from_basis(&mut n, &x, &y, &z);
+        assert_close(&m, &n, 0f64);
+    }
+
+    // copied as given, neither normalized nor made right handed, and
+    // whatever was in the rest of `out` is cleared
+    m = [9f64; 16];
+    from_basis(&mut m, &[2f64, 0f64, 0f64], &[1f64, 1f64, 0f64], &[0f64, 0f64, -1f64]);
+    assert_eq!(m, [
+        2f64, 0f64, 0f64, 0f64,
+        1f64, 1f64, 0f64, 0f64,
+        0f64, 0f64, -1f64, 0f64,
+        0f64, 0f64, 0f64, 1f64
+    ]);
+}
+
 #[inline]
 pub fn frustum<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T) -> &'a mut [T; 16] {
     let x = (T::from_isize(2isize) * near) / (right - left);
@@ -557,3 +1687,236 @@ pub fn orthographic<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, righ
 
     out
 }
+
+/// Same as `frustum` for the given clip space. With `clip.infinite` the far
+/// plane is ignored and pushed to infinity.
+#[inline]
+pub fn frustum_clip<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T, clip: &'b ClipSpace) -> &'a mut [T; 16] {
+    let (zn, zf) = clip.near_far::<T>();
+
+    frustum(out, top, right, bottom, left, near, far);
+
+    if clip.infinite {
+        out[10] = -zf;
+        out[14] = near * (zn - zf);
+    } else {
+        out[10] = (far * zf - near * zn) / (near - far);
+        out[14] = (near * far * (zf - zn)) / (near - far);
+    }
+    out
+}
+#[test]
+fn test_frustum_clip() {
+    let configs = [
+        ClipSpace::opengl(),
+        ClipSpace::opengl().reversed(),
+        ClipSpace::zero_to_one(),
+        ClipSpace::zero_to_one().reversed(),
+    ];
+    let mut m = [0f64; 16];
+    let mut gl = [0f64; 16];
+    let mut p = [0f64; 3];
+
+    for clip in configs.iter() {
+        let (zn, zf) = clip.near_far::<f64>();
+
+        frustum_clip(&mut m, 1f64, 2f64, -1f64, -2f64, 0.5f64, 100f64, clip);
+        ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -0.5f64]);
+        assert!((p[2] - zn).abs() < 1e-12f64);
+        ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -100f64]);
+        assert!((p[2] - zf).abs() < 1e-12f64);
+
+        frustum_clip(&mut m, 1f64, 2f64, -1f64, -2f64, 0.5f64, 100f64, &clip.infinite());
+        ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -0.5f64]);
+        assert!((p[2] - zn).abs() < 1e-12f64);
+        ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -1e12f64]);
+        assert!((p[2] - zf).abs() < 1e-9f64);
+    }
+
+    frustum(&mut gl, 1f64, 2f64, -1f64, -2f64, 0.5f64, 100f64);
+    frustum_clip(&mut m, 1f64, 2f64, -1f64, -2f64, 0.5f64, 100f64, &ClipSpace::opengl());
+    assert_eq!(m, gl);
+}
+
+#[inline]
+pub fn perspective_clip<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], fov: T, aspect: T, near: T, far: T, clip: &'b ClipSpace) -> &'a mut [T; 16] {
+    let ymax = near * (fov / T::from_isize(2isize)).tan();
+    let ymin = -ymax;
+    let xmin = ymin * aspect;
+    let xmax = ymax * aspect;
+
+    frustum_clip(out, ymax, xmax, ymin, xmin, near, far, clip)
+}
+#[test]
+fn test_perspective_clip() {
+    let mut m = [0f64; 16];
+    let mut p = [0f64; 3];
+    let clip = ClipSpace::zero_to_one().reversed().infinite();
+    perspective_clip(&mut m, 1f64, 1.5f64, 0.1f64, 0f64, &clip);
+    ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -0.1f64]);
+    assert!((p[2] - 1f64).abs() < 1e-12f64);
+    ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -1f64]);
+    assert!((p[2] - 0.1f64).abs() < 1e-12f64);
+    assert_eq!(m[10], 0f64);
+}
+
+/// Same as `orthographic` for the given clip space. `clip.infinite` has no
+/// meaning for orthographic projections and is ignored.
+#[inline]
+pub fn orthographic_clip<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T, clip: &'b ClipSpace) -> &'a mut [T; 16] {
+    let (zn, zf) = clip.near_far::<T>();
+    let a = (zn - zf) / (far - near);
+
+    orthographic(out, top, right, bottom, left, near, far);
+
+    out[10] = a;
+    out[14] = zn + a * near;
+    out
+}
+#[test]
+fn test_orthographic_clip() {
+    let configs = [
+        ClipSpace::opengl(),
+        ClipSpace::opengl().reversed(),
+        ClipSpace::zero_to_one(),
+        ClipSpace::zero_to_one().reversed(),
+    ];
+    let mut m = [0f64; 16];
+    let mut p = [0f64; 3];
+
+    for clip in configs.iter() {
+        let (zn, zf) = clip.near_far::<f64>();
+
+        orthographic_clip(&mut m, 1f64, 2f64, -1f64, -2f64, 1f64, 9f64, clip);
+        ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -1f64]);
+        assert_eq!(p[2], zn);
+        ::point::transform_point3(&mut p, &m, &[0f64, 0f64, -9f64]);
+        assert_eq!(p[2], zf);
+    }
+}
+
+// a left-handed projection is the right-handed one applied to z-mirrored
+// view space, i.e. with its third column negated
+#[inline]
+fn mirror_z<'a, T: Copy + Signed>(out: &'a mut [T; 16]) -> &'a mut [T; 16] {
+    out[8] = -out[8];
+    out[9] = -out[9];
+    out[10] = -out[10];
+    out[11] = -out[11];
+    out
+}
+
+#[inline]
+pub fn frustum_rh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T) -> &'a mut [T; 16] {
+    frustum(out, top, right, bottom, left, near, far)
+}
+
+/// Left-handed `frustum`, for view spaces looking down +Z.
+#[inline]
+pub fn frustum_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T) -> &'a mut [T; 16] {
+    mirror_z(frustum(out, top, right, bottom, left, near, far))
+}
+
+#[inline]
+pub fn frustum_clip_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T, clip: &'b ClipSpace) -> &'a mut [T; 16] {
+    mirror_z(frustum_clip(out, top, right, bottom, left, near, far, clip))
+}
+#[test]
+fn test_frustum_lh() {
+    let mut lh = [0f64; 16];
+    let mut rh = [0f64; 16];
+    let mut mirrored = [0f64; 16];
+    let mut p = [0f64; 3];
+
+    frustum_lh(&mut lh, 1f64, 2f64, -1f64, -3f64, 0.5f64, 20f64);
+    frustum_rh(&mut rh, 1f64, 2f64, -1f64, -3f64, 0.5f64, 20f64);
+    ::mul::mul(&mut mirrored, &rh, &MIRROR_Z);
+    assert_eq!(lh, mirrored);
+
+    let clip = ClipSpace::zero_to_one();
+    frustum_clip_lh(&mut lh, 1f64, 1f64, -1f64, -1f64, 0.5f64, 20f64, &clip);
+    ::point::transform_point3(&mut p, &lh, &[0f64, 0f64, 0.5f64]);
+    assert!(p[2].abs() < 1e-12f64);
+    ::point::transform_point3(&mut p, &lh, &[40f64, 40f64, 20f64]);
+    assert_close(&p, &[1f64, 1f64, 1f64], 1e-12f64);
+}
+
+#[inline]
+pub fn perspective_rh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], fov: T, aspect: T, near: T, far: T) -> &'a mut [T; 16] {
+    perspective(out, fov, aspect, near, far)
+}
+
+/// Left-handed `perspective`, for view spaces looking down +Z.
+#[inline]
+pub fn perspective_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], fov: T, aspect: T, near: T, far: T) -> &'a mut [T; 16] {
+    mirror_z(perspective(out, fov, aspect, near, far))
+}
+
+#[inline]
+pub fn perspective_clip_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], fov: T, aspect: T, near: T, far: T, clip: &'b ClipSpace) -> &'a mut [T; 16] {
+    mirror_z(perspective_clip(out, fov, aspect, near, far, clip))
+}
+#[test]
+fn test_perspective_lh() {
+    let mut lh = [0f64; 16];
+    let mut rh = [0f64; 16];
+    let mut view = [0f64; 16];
+    let mut a = [0f64; 16];
+    let mut b = [0f64; 16];
+    let mut tmp = [0f64; 16];
+    let mut p = [0f64; 3];
+    let mut q = [0f64; 3];
+
+    // the same scene described in left and right-handed world coordinates
+    // lands on the same clip space points
+    perspective_lh(&mut lh, 1f64, 1.5f64, 0.1f64, 100f64);
+    perspective_rh(&mut rh, 1f64, 1.5f64, 0.1f64, 100f64);
+    look_at_lh(&mut view, &[1f64, 2f64, 3f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    ::mul::mul(&mut a, &lh, &view);
+    look_at_rh(&mut view, &[1f64, 2f64, -3f64], &[0f64, 0f64, 0f64], &[0f64, 1f64, 0f64]);
+    ::mul::mul(&mut tmp, &rh, &view);
+    ::mul::mul(&mut b, &tmp, &MIRROR_Z);
+    assert_close(&a, &b, 1e-12f64);
+
+    ::point::transform_point3(&mut p, &a, &[0.5f64, 0.25f64, -1f64]);
+    ::point::transform_point3(&mut q, &b, &[0.5f64, 0.25f64, -1f64]);
+    assert_close(&p, &q, 1e-12f64);
+
+    perspective_clip_lh(&mut lh, 1f64, 1.5f64, 0.1f64, 100f64, &ClipSpace::zero_to_one().reversed());
+    ::point::transform_point3(&mut p, &lh, &[0f64, 0f64, 0.1f64]);
+    assert!((p[2] - 1f64).abs() < 1e-12f64);
+}
+
+#[inline]
+pub fn orthographic_rh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T) -> &'a mut [T; 16] {
+    orthographic(out, top, right, bottom, left, near, far)
+}
+
+/// Left-handed `orthographic`, for view spaces looking down +Z.
+#[inline]
+pub fn orthographic_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T) -> &'a mut [T; 16] {
+    mirror_z(orthographic(out, top, right, bottom, left, near, far))
+}
+
+#[inline]
+pub fn orthographic_clip_lh<'a, 'b, T: Copy + Signed>(out: &'a mut [T; 16], top: T, right: T, bottom: T, left: T, near: T, far: T, clip: &'b ClipSpace) -> &'a mut [T; 16] {
+    mirror_z(orthographic_clip(out, top, right, bottom, left, near, far, clip))
+}
+#[test]
+fn test_orthographic_lh() {
+    let mut lh = [0f64; 16];
+    let mut rh = [0f64; 16];
+    let mut mirrored = [0f64; 16];
+    let mut p = [0f64; 3];
+
+    orthographic_lh(&mut lh, 1f64, 2f64, -1f64, -2f64, 1f64, 9f64);
+    orthographic_rh(&mut rh, 1f64, 2f64, -1f64, -2f64, 1f64, 9f64);
+    ::mul::mul(&mut mirrored, &rh, &MIRROR_Z);
+    assert_eq!(lh, mirrored);
+
+    orthographic_clip_lh(&mut lh, 1f64, 2f64, -1f64, -2f64, 1f64, 9f64, &ClipSpace::zero_to_one());
+    ::point::transform_point3(&mut p, &lh, &[0f64, 0f64, 1f64]);
+    assert_eq!(p[2], 0f64);
+    ::point::transform_point3(&mut p, &lh, &[0f64, 0f64, 9f64]);
+    assert_eq!(p[2], 1f64);
+}
@@ -0,0 +1,40 @@
+// xorshift32: a small reproducible generator that needs no std, shared by
+// `fit::ransac` and the tests. `state` must not be zero.
+
+#[inline]
+pub fn next(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+// uniform in `0..n` by multiply-shift, redrawing the few values that would
+// otherwise favour the low results
+#[inline]
+pub fn below(state: &mut u32, n: usize) -> usize {
+    assert!(n > 0 && n as u32 as usize == n);
+    let n = n as u32;
+    let threshold = n.wrapping_neg() % n;
+    loop {
+        let m = next(state) as u64 * n as u64;
+        if m as u32 >= threshold {
+            return (m >> 32) as usize;
+        }
+    }
+}
+#[test]
+fn test_xorshift() {
+    let mut state = 1u32;
+    assert_eq!(next(&mut state), 270369u32);
+    assert_eq!(next(&mut state), 67634689u32);
+
+    let mut counts = [0usize; 3];
+    for _ in 0..30000 {
+        counts[below(&mut state, 3)] += 1;
+    }
+    for &c in counts.iter() {
+        assert!(c > 9500 && c < 10500, "{:?}", counts);
+    }
+    assert_eq!(below(&mut state, 1), 0);
+}